@@ -1,4 +1,7 @@
-use utils::FileReader;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use utils::{Args, FileReader};
 
 struct Dial {
     loc: i32,
@@ -21,9 +24,28 @@ enum Rotation {
     Right(i32),
 }
 
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rotation::Left(amt) => write!(f, "L{}", amt),
+            Rotation::Right(amt) => write!(f, "R{}", amt),
+        }
+    }
+}
+
+// What happened to the dial during a single turn
+struct TurnEvent {
+    rotation: Rotation,
+    zero_clicks: i32,
+    stopped_at_zero: bool,
+    loc: i32,
+}
+
 impl Dial {
-    pub fn turn(&mut self, rotation: Rotation) {
+    pub fn turn(&mut self, rotation: Rotation) -> TurnEvent {
         let prev_loc = self.loc;
+        let prev_zero_stops = self.num_zero_stops;
+        let prev_zero_clicks = self.num_zero_clicks;
         match rotation {
             Rotation::Left(amt) => {
                 self.loc -= amt;
@@ -63,23 +85,105 @@ impl Dial {
             self.num_zero_stops += 1;
             self.num_zero_clicks += 1;
         }
+        TurnEvent {
+            rotation,
+            zero_clicks: self.num_zero_clicks - prev_zero_clicks,
+            stopped_at_zero: self.num_zero_stops != prev_zero_stops,
+            loc: self.loc,
+        }
     }
 }
 
-fn parse(line: &str) -> Option<Rotation> {
-    match &line[0..1] {
-        "L" => Some(Rotation::Left(line[1..].parse().unwrap())),
-        "R" => Some(Rotation::Right(line[1..].parse().unwrap())),
-        _ => None,
+#[derive(Debug)]
+enum ParseRotationError {
+    Empty,
+    UnknownDirection(char),
+    // The direction already gives the sign, so "L-5" or "R+5" is ambiguous
+    SignedAmount(char),
+    InvalidAmount(ParseIntError),
+}
+
+impl fmt::Display for ParseRotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseRotationError::Empty => write!(f, "empty rotation"),
+            ParseRotationError::UnknownDirection(c) => write!(f, "unknown direction '{}'", c),
+            ParseRotationError::SignedAmount(sign) => {
+                write!(f, "amount can't have a sign ('{}')", sign)
+            }
+            ParseRotationError::InvalidAmount(e) => write!(f, "invalid amount ({})", e),
+        }
     }
 }
 
+impl FromStr for Rotation {
+    type Err = ParseRotationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        let make_rotation = match chars.next() {
+            None => return Err(ParseRotationError::Empty),
+            Some('L') => Rotation::Left,
+            Some('R') => Rotation::Right,
+            Some(c) => return Err(ParseRotationError::UnknownDirection(c)),
+        };
+        if let Some(sign @ ('+' | '-')) = chars.clone().next() {
+            return Err(ParseRotationError::SignedAmount(sign));
+        }
+        chars
+            .as_str()
+            .parse()
+            .map(make_rotation)
+            .map_err(ParseRotationError::InvalidAmount)
+    }
+}
+
+// Parses every line into a rotation, paired with its (1-indexed) line number.
+// In strict mode, all malformed lines are reported and the program exits;
+// otherwise, they are skipped
+fn parse_rotations(lines: impl Iterator<Item = String>, strict: bool) -> Vec<(usize, Rotation)> {
+    let mut rotations = vec![];
+    let mut num_errors = 0;
+    for (idx, line) in lines.enumerate() {
+        match line.parse::<Rotation>() {
+            Ok(rot) => rotations.push((idx + 1, rot)),
+            Err(e) if strict => {
+                eprintln!("Line {}: {} in \"{}\"", idx + 1, e, line);
+                num_errors += 1;
+            }
+            Err(_) => (),
+        }
+    }
+    if num_errors > 0 {
+        eprintln!("Aborting due to {} malformed line(s)", num_errors);
+        std::process::exit(1);
+    }
+    rotations
+}
+
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let finished_dial = FileReader::new(file_name.as_str())
-        .filter_map(|line| parse(line.as_str()))
-        .fold(Dial::default(), |mut dial, rot| {
-            dial.turn(rot);
+    let args = Args::new();
+    let file_name = args
+        .positional(0)
+        .expect("Usage: <binary> input.txt [--trace] [--strict]");
+    let trace = args.flag("trace");
+    let finished_dial = parse_rotations(FileReader::new(file_name), args.flag("strict"))
+        .into_iter()
+        .fold(Dial::default(), |mut dial, (line_num, rot)| {
+            let event = dial.turn(rot);
+            if trace && event.zero_clicks > 0 {
+                println!(
+                    "[Trace] Line {}: {} passed zero {} time(s){}, now at {}",
+                    line_num,
+                    event.rotation,
+                    event.zero_clicks,
+                    if event.stopped_at_zero {
+                        " (stopped at zero)"
+                    } else {
+                        ""
+                    },
+                    event.loc
+                );
+            }
             dial
         });
     println! {"[Part1] Ended at zero {} times!", finished_dial.num_zero_stops}
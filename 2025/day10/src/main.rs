@@ -1,6 +1,240 @@
-use good_lp::{Expression, ProblemVariables, Solution, SolverModel, Variable, microlp, variable};
+use good_lp::{Expression, ProblemVariables, Solution, Solver, SolverModel, Variable, variable};
+use num_rational::{Ratio, Rational64};
+use num_traits::{Signed, Zero};
+use std::cmp::{max, min};
 use std::collections::HashSet;
-use utils::FileReader;
+use std::str::FromStr;
+use utils::{Args, FileReader, MapReduce};
+
+// Finds the fewest presses of each button that meet a machine's joltage
+// requirements
+trait JoltageSolver: Sync {
+    fn name(&self) -> &'static str;
+    fn presses(&self, machine: &Machine) -> Result<Vec<usize>, String>;
+}
+
+// Integer linear programming through one of good_lp's solvers
+struct LpSolver<S> {
+    name: &'static str,
+    solver: S,
+}
+
+impl<S: Solver + Copy + Sync> JoltageSolver for LpSolver<S> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn presses(&self, machine: &Machine) -> Result<Vec<usize>, String> {
+        let mut problem = ProblemVariables::new();
+        let variables: Vec<Variable> =
+            problem.add_all(vec![variable().integer().min(0); machine.buttons.len()]);
+        let objective: Expression = variables.iter().sum();
+        let mut model = problem.minimise(objective).using(self.solver);
+        for (idx, sum) in machine.joltage_reqs.iter().enumerate() {
+            let mut sum_expr: Expression = Expression::from(0);
+            for (button_idx, button) in machine.buttons.iter().enumerate() {
+                if button.contains(&idx) {
+                    sum_expr = variables[button_idx] + sum_expr;
+                }
+            }
+            model = model.with(sum_expr.eq(Expression::from(*sum as i32)));
+        }
+        let solution = model
+            .solve()
+            .map_err(|e| format!("{} failed: {}", self.name, e))?;
+        round_presses(
+            self.name,
+            variables.into_iter().map(|var| solution.value(var)),
+        )
+    }
+}
+
+// Floating-point solvers only give approximately whole presses, so anything
+// too far from a whole, non-negative number is reported rather than rounded
+fn round_presses(name: &str, values: impl Iterator<Item = f64>) -> Result<Vec<usize>, String> {
+    values
+        .map(|value| {
+            let rounded = value.round();
+            if (value - rounded).abs() > 1e-6 || rounded < 0.0 {
+                Err(format!("{} returned {} presses for a button", name, value))
+            } else {
+                Ok(rounded as usize)
+            }
+        })
+        .collect()
+}
+
+// Gaussian elimination over rationals, then a branch and bound search over
+// the presses, bounded at each step by the fewest presses when fractional
+// presses are allowed (found exactly, with the simplex method over
+// rationals)
+struct ExactSolver;
+
+impl JoltageSolver for ExactSolver {
+    fn name(&self) -> &'static str {
+        "exact"
+    }
+
+    fn presses(&self, machine: &Machine) -> Result<Vec<usize>, String> {
+        // Buttons that raise the same counters are interchangeable, so only
+        // search over one of each, and give it all of their presses
+        let mut merged = Machine {
+            goal: vec![],
+            buttons: vec![],
+            joltage_reqs: machine.joltage_reqs.clone(),
+        };
+        let merged_idxs: Vec<usize> = machine
+            .buttons
+            .iter()
+            .map(|button| {
+                let mut counters = button.clone();
+                counters.sort_unstable();
+                counters.dedup();
+                match merged.buttons.iter().position(|other| *other == counters) {
+                    Some(idx) => idx,
+                    None => {
+                        merged.buttons.push(counters);
+                        merged.buttons.len() - 1
+                    }
+                }
+            })
+            .collect();
+        let system = ReducedSystem::new(&merged)
+            .ok_or("infeasible (joltage requirements are inconsistent)".to_string())?;
+        let mut search = PressSearch::new(merged.press_bounds());
+        search.run(&system);
+        let (_, mut merged_presses) = search
+            .best
+            .ok_or("infeasible (no non-negative integer solution)".to_string())?;
+        Ok(merged_idxs
+            .iter()
+            .map(|idx| std::mem::take(&mut merged_presses[*idx]))
+            .collect())
+    }
+}
+
+// good_lp's lp_solve wrapper doesn't expose the model before solving, so
+// there's no way to turn off lp_solve's logging (which goes to stdout). We
+// build the model with the lpsolve crate directly instead
+#[cfg(feature = "lpsolve")]
+struct LpSolveSolver;
+
+#[cfg(feature = "lpsolve")]
+impl JoltageSolver for LpSolveSolver {
+    fn name(&self) -> &'static str {
+        "lpsolve"
+    }
+
+    fn presses(&self, machine: &Machine) -> Result<Vec<usize>, String> {
+        let num_buttons = machine.buttons.len();
+        let mut problem = lpsolve::Problem::new(0, num_buttons as i32)
+            .ok_or("lpsolve couldn't create a model".to_string())?;
+        problem.set_verbose(lpsolve::Verbosity::Critical);
+        // Coefficients are 1-indexed; the first entry is ignored
+        let mut objective = vec![1.0; num_buttons + 1];
+        problem
+            .set_objective_function(&mut objective)
+            .map_err(|e| format!("lpsolve failed: {:?}", e))?;
+        problem.set_minimize();
+        for col in 1..=num_buttons as i32 {
+            problem
+                .set_integer(col, true)
+                .map_err(|e| format!("lpsolve failed: {:?}", e))?;
+        }
+        for (idx, sum) in machine.joltage_reqs.iter().enumerate() {
+            let mut coeffs: Vec<f64> = std::iter::once(0.0)
+                .chain(
+                    machine
+                        .buttons
+                        .iter()
+                        .map(|button| button.contains(&idx) as u8 as f64),
+                )
+                .collect();
+            problem
+                .add_constraint(&mut coeffs, *sum as f64, lpsolve::ConstraintType::Eq)
+                .map_err(|e| format!("lpsolve failed: {:?}", e))?;
+        }
+        match problem.solve() {
+            lpsolve::SolveStatus::Optimal | lpsolve::SolveStatus::Presolved => (),
+            status => return Err(format!("lpsolve failed: {:?}", status)),
+        }
+        let mut values = vec![0.0; num_buttons];
+        problem
+            .get_solution_variables(&mut values)
+            .ok_or("lpsolve returned too few variables".to_string())?;
+        round_presses(self.name(), values.into_iter())
+    }
+}
+
+// Solvers other than microlp and exact are behind the Cargo feature of the
+// same name. Unlike microlp, these wrap native libraries: lpsolve compiles
+// its bundled C sources, highs needs cmake and a C++ compiler to build
+// HiGHS, and cbc links against the system's Cbc libraries
+#[derive(Clone, Copy)]
+enum Backend {
+    Exact,
+    Microlp,
+    #[cfg(feature = "highs")]
+    Highs,
+    #[cfg(feature = "lpsolve")]
+    LpSolve,
+    #[cfg(feature = "cbc")]
+    Cbc,
+}
+
+fn missing_feature(name: &str) -> String {
+    format!(
+        "Solver {} requires building with `--features {}`",
+        name, name
+    )
+}
+
+impl FromStr for Backend {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Backend::Exact),
+            "lp" | "microlp" => Ok(Backend::Microlp),
+            #[cfg(feature = "highs")]
+            "highs" => Ok(Backend::Highs),
+            #[cfg(not(feature = "highs"))]
+            "highs" => Err(missing_feature(s)),
+            #[cfg(feature = "lpsolve")]
+            "lpsolve" => Ok(Backend::LpSolve),
+            #[cfg(not(feature = "lpsolve"))]
+            "lpsolve" => Err(missing_feature(s)),
+            #[cfg(feature = "cbc")]
+            "cbc" => Ok(Backend::Cbc),
+            #[cfg(not(feature = "cbc"))]
+            "cbc" => Err(missing_feature(s)),
+            _ => Err(format!("Invalid solver: {}", s)),
+        }
+    }
+}
+
+impl Backend {
+    fn solver(self) -> Box<dyn JoltageSolver> {
+        match self {
+            Backend::Exact => Box::new(ExactSolver),
+            Backend::Microlp => Box::new(LpSolver {
+                name: "microlp",
+                solver: good_lp::microlp,
+            }),
+            #[cfg(feature = "highs")]
+            Backend::Highs => Box::new(LpSolver {
+                name: "highs",
+                solver: good_lp::highs,
+            }),
+            #[cfg(feature = "lpsolve")]
+            Backend::LpSolve => Box::new(LpSolveSolver),
+            #[cfg(feature = "cbc")]
+            Backend::Cbc => Box::new(LpSolver {
+                name: "cbc",
+                solver: good_lp::coin_cbc,
+            }),
+        }
+    }
+}
 
 #[derive(Debug)]
 struct Machine {
@@ -40,7 +274,7 @@ impl From<String> for Machine {
         Self {
             goal,
             buttons: remaining_entries,
-            joltage_reqs: joltage_reqs,
+            joltage_reqs,
         }
     }
 }
@@ -76,47 +310,665 @@ impl Machine {
             loop_iters += 1;
         }
     }
-    pub fn num_joltage_presses(&self) -> usize {
-        let mut problem = ProblemVariables::new();
-        let variables: Vec<Variable> =
-            problem.add_all(vec![variable().integer().min(0); self.buttons.len()]);
-        let objective: Expression = variables.iter().sum();
-        let mut model = problem.minimise(objective).using(microlp);
-        for (idx, sum) in self.joltage_reqs.iter().enumerate() {
-            let mut sum_expr: Expression = Expression::from(0);
-            for (button_idx, button) in self.buttons.iter().enumerate() {
-                if button.contains(&idx) {
-                    sum_expr = variables[button_idx] + sum_expr;
+    pub fn num_joltage_presses(&self, solver: &dyn JoltageSolver) -> Result<usize, String> {
+        let presses = solver.presses(self)?;
+        if !self.meets_joltage_reqs(&presses) {
+            return Err(format!(
+                "{} solution doesn't meet the joltage requirements",
+                solver.name()
+            ));
+        }
+        Ok(presses.iter().sum())
+    }
+
+    fn meets_joltage_reqs(&self, presses: &[usize]) -> bool {
+        self.joltage_reqs.iter().enumerate().all(|(idx, req)| {
+            self.buttons
+                .iter()
+                .zip(presses)
+                .filter(|(button, _)| button.contains(&idx))
+                .map(|(_, num_presses)| num_presses)
+                .sum::<usize>()
+                == *req
+        })
+    }
+
+    // A button can't be pressed more times than any counter it raises allows
+    fn press_bounds(&self) -> Vec<i64> {
+        self.buttons
+            .iter()
+            .map(|button| {
+                button
+                    .iter()
+                    .map(|idx| self.joltage_reqs[*idx] as i64)
+                    .min()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+}
+
+// A row of the reduced system, scaled to integers:
+// `scale * presses[col] + sum(free_coefs[i] * presses[free[i]]) = rhs`
+struct PivotRow {
+    col: usize,
+    scale: i64,
+    rhs: i64,
+    free_coefs: Vec<i64>,
+}
+
+// The joltage requirements (one row per counter, one column per button) in
+// reduced row echelon form, keeping only the rows with a pivot
+struct ReducedSystem {
+    rows: Vec<PivotRow>,
+    // Columns without a pivot, whose presses decide the pivot buttons'
+    free: Vec<usize>,
+    // The total presses, scaled by `denom`, is
+    // `sum(row_weights[r] * rhs[r])` with each free button adding
+    // `free_costs[i]` per press (negative if pressing it saves more pivot
+    // presses than it costs)
+    denom: i64,
+    row_weights: Vec<i64>,
+    free_costs: Vec<i64>,
+}
+
+impl ReducedSystem {
+    // Eliminates over rationals, so there's no rounding. Returns None if the
+    // requirements can't be met, even allowing fractional or negative presses
+    fn new(machine: &Machine) -> Option<Self> {
+        let num_buttons = machine.buttons.len();
+        let mut rows: Vec<Vec<Rational64>> = machine
+            .joltage_reqs
+            .iter()
+            .enumerate()
+            .map(|(idx, req)| {
+                let mut row: Vec<Rational64> = machine
+                    .buttons
+                    .iter()
+                    .map(|button| Rational64::from(button.contains(&idx) as i64))
+                    .collect();
+                row.push(Rational64::from(*req as i64));
+                row
+            })
+            .collect();
+        let mut pivots = vec![];
+        for col in 0..num_buttons {
+            let row = pivots.len();
+            let Some(pivot_row) = (row..rows.len()).find(|r| !rows[*r][col].is_zero()) else {
+                continue;
+            };
+            rows.swap(row, pivot_row);
+            let pivot = rows[row][col];
+            rows[row].iter_mut().for_each(|entry| *entry /= pivot);
+            let pivot_row = rows[row].clone();
+            for (other_idx, other) in rows.iter_mut().enumerate() {
+                let factor = other[col];
+                if other_idx != row && !factor.is_zero() {
+                    for (entry, pivot_entry) in other.iter_mut().zip(pivot_row.iter()) {
+                        *entry -= factor * pivot_entry;
+                    }
                 }
             }
-            model = model.with(sum_expr.eq(Expression::from(*sum as i32)));
+            pivots.push(col);
+        }
+        if rows[pivots.len()..]
+            .iter()
+            .any(|row| !row[num_buttons].is_zero())
+        {
+            return None;
         }
-        let solution = model.solve().unwrap();
-        variables
-            .into_iter()
-            .map(|var| solution.value(var).round() as i32)
-            .fold(0, |acc, element| acc + element) as usize
+        let free: Vec<usize> = (0..num_buttons)
+            .filter(|col| !pivots.contains(col))
+            .collect();
+        let rows: Vec<PivotRow> = rows
+            .iter()
+            .zip(pivots)
+            .map(|(row, col)| {
+                // Clear the denominators so the search only needs integers
+                let scale = row
+                    .iter()
+                    .fold(1, |scale, entry| num_integer::lcm(scale, *entry.denom()));
+                let scaled = |entry: &Rational64| (entry * scale).to_integer();
+                PivotRow {
+                    col,
+                    scale,
+                    rhs: scaled(&row[num_buttons]),
+                    free_coefs: free
+                        .iter()
+                        .map(|free_col| scaled(&row[*free_col]))
+                        .collect(),
+                }
+            })
+            .collect();
+        let denom = rows
+            .iter()
+            .fold(1, |denom, row| num_integer::lcm(denom, row.scale));
+        let row_weights: Vec<i64> = rows.iter().map(|row| denom / row.scale).collect();
+        let free_costs = (0..free.len())
+            .map(|free_idx| {
+                denom
+                    - rows
+                        .iter()
+                        .zip(row_weights.iter())
+                        .map(|(row, weight)| weight * row.free_coefs[free_idx])
+                        .sum::<i64>()
+            })
+            .collect();
+        Some(Self {
+            rows,
+            free,
+            denom,
+            row_weights,
+            free_costs,
+        })
+    }
+
+    // The total presses (scaled by `denom`) when no free button is pressed
+    fn pivot_total(&self) -> i64 {
+        self.rows
+            .iter()
+            .zip(self.row_weights.iter())
+            .map(|(row, weight)| weight * row.rhs)
+            .sum()
+    }
+}
+
+fn div_floor(num: i64, denom: i64) -> i64 {
+    let quotient = num / denom;
+    if (num % denom != 0) && ((num < 0) != (denom < 0)) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn div_ceil(num: i64, denom: i64) -> i64 {
+    -div_floor(-num, denom)
+}
+
+struct PressSearch {
+    // The presses each button is still allowed, narrowed as the search
+    // branches
+    bounds: Vec<(i64, i64)>,
+    best: Option<(i64, Vec<usize>)>,
+}
+
+impl PressSearch {
+    fn new(bounds: Vec<i64>) -> Self {
+        Self {
+            bounds: bounds.into_iter().map(|bound| (0, bound)).collect(),
+            best: None,
+        }
+    }
+
+    // Branch and bound: finds the fewest presses with fractional presses
+    // allowed, and if some button's presses aren't whole, searches the
+    // presses below them and those above them separately, keeping the
+    // fewest total presses. Branches are cut off once they can't keep the
+    // buttons in bounds, or can't beat the best total so far
+    fn run(&mut self, system: &ReducedSystem) {
+        let Some(ranges) = self.free_ranges(system) else {
+            return;
+        };
+        let Some((lowest_total, relaxed)) = self.relaxation(system, &ranges) else {
+            return;
+        };
+        if self
+            .best
+            .as_ref()
+            .is_some_and(|(best_total, _)| lowest_total >= *best_total)
+        {
+            return;
+        }
+        let mut presses = vec![Ratio::zero(); self.bounds.len()];
+        for (col, value) in system.free.iter().zip(relaxed) {
+            presses[*col] = value;
+        }
+        for row in system.rows.iter() {
+            let free_sum: Ratio<i128> = row
+                .free_coefs
+                .iter()
+                .zip(system.free.iter())
+                .map(|(coef, col)| presses[*col] * (*coef as i128))
+                .sum();
+            presses[row.col] = (Ratio::from(row.rhs as i128) - free_sum) / (row.scale as i128);
+        }
+        // Branch on the free buttons first, since they decide the rest
+        let Some(col) = system
+            .free
+            .iter()
+            .copied()
+            .chain(system.rows.iter().map(|row| row.col))
+            .find(|col| !presses[*col].is_integer())
+        else {
+            let presses = presses.iter().map(|p| p.to_integer() as usize).collect();
+            self.best = Some((lowest_total, presses));
+            return;
+        };
+        let saved = self.bounds.clone();
+        for (free_col, range) in system.free.iter().zip(ranges) {
+            self.bounds[*free_col] = range;
+        }
+        let (low, high) = self.bounds[col];
+        let (below, above) = (
+            presses[col].floor().to_integer() as i64,
+            presses[col].ceil().to_integer() as i64,
+        );
+        // Try the side the relaxation leans towards first, to find a good
+        // solution (and so cut off more branches) sooner
+        let mut sides = [(low, below), (above, high)];
+        if presses[col] - presses[col].floor() > Ratio::new(1, 2) {
+            sides.reverse();
+        }
+        for side in sides {
+            self.bounds[col] = side;
+            self.run(system);
+        }
+        self.bounds = saved;
+    }
+
+    // Narrows the presses of each free button to those that could still
+    // keep every pivot button in bounds and beat the best total so far,
+    // given the others' ranges, until nothing changes. Returns None if some
+    // range is empty
+    fn free_ranges(&self, system: &ReducedSystem) -> Option<Vec<(i64, i64)>> {
+        // Each constraint is that the free presses, weighted by the
+        // coefficients, add up to within the target range
+        let mut constraints: Vec<(&[i64], i64, i64)> = system
+            .rows
+            .iter()
+            .map(|row| {
+                let (low, high) = self.bounds[row.col];
+                (
+                    &row.free_coefs[..],
+                    row.rhs - row.scale * high,
+                    row.rhs - row.scale * low,
+                )
+            })
+            .collect();
+        if let Some((best_total, _)) = self.best.as_ref() {
+            constraints.push((
+                &system.free_costs[..],
+                i64::MIN / 4,
+                system.denom * (best_total - 1) - system.pivot_total(),
+            ));
+        }
+        let mut ranges: Vec<(i64, i64)> = system.free.iter().map(|col| self.bounds[*col]).collect();
+        let extremes = |coef: i64, (low, high): (i64, i64)| {
+            (min(coef * low, coef * high), max(coef * low, coef * high))
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (coefs, target_low, target_high) in constraints.iter() {
+                let (min_sum, max_sum) = coefs
+                    .iter()
+                    .zip(ranges.iter())
+                    .map(|(coef, range)| extremes(*coef, *range))
+                    .fold((0, 0), |acc, (low, high)| (acc.0 + low, acc.1 + high));
+                if min_sum > *target_high || max_sum < *target_low {
+                    return None;
+                }
+                for (idx, coef) in coefs.iter().enumerate().filter(|(_, coef)| **coef != 0) {
+                    let (own_min, own_max) = extremes(*coef, ranges[idx]);
+                    let (term_low, term_high) = (
+                        target_low - (max_sum - own_max),
+                        target_high - (min_sum - own_min),
+                    );
+                    let (new_low, new_high) = if *coef > 0 {
+                        (div_ceil(term_low, *coef), div_floor(term_high, *coef))
+                    } else {
+                        (div_ceil(term_high, *coef), div_floor(term_low, *coef))
+                    };
+                    let (low, high) = ranges[idx];
+                    if new_low > low || new_high < high {
+                        ranges[idx] = (max(low, new_low), min(high, new_high));
+                        if ranges[idx].0 > ranges[idx].1 {
+                            return None;
+                        }
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Some(ranges)
+    }
+
+    // Finds the fewest presses with fractional presses allowed, which can't
+    // be more than with whole ones. Returns that total (rounded up) and each
+    // free button's presses, or None if even fractional presses can't meet
+    // the requirements within bounds
+    fn relaxation(
+        &self,
+        system: &ReducedSystem,
+        ranges: &[(i64, i64)],
+    ) -> Option<(i64, Vec<Ratio<i128>>)> {
+        // Shift each free button's presses to start at 0, and leave out the
+        // ones with no choice left
+        let vars: Vec<usize> = (0..ranges.len())
+            .filter(|idx| ranges[*idx].0 < ranges[*idx].1)
+            .collect();
+        let mut constraints: Vec<(Vec<i64>, i64)> = vec![];
+        // Each pivot button must stay within its bounds
+        for row in system.rows.iter() {
+            let (low, high) = self.bounds[row.col];
+            let remainder = row.rhs
+                - ranges
+                    .iter()
+                    .zip(row.free_coefs.iter())
+                    .map(|(range, coef)| coef * range.0)
+                    .sum::<i64>();
+            let coefs: Vec<i64> = vars.iter().map(|var| row.free_coefs[*var]).collect();
+            let negated = coefs.iter().map(|coef| -coef).collect();
+            constraints.push((coefs, remainder - row.scale * low));
+            constraints.push((negated, row.scale * high - remainder));
+        }
+        // Drop the constraints that hold anywhere in the ranges
+        constraints.retain(|(coefs, rhs)| {
+            coefs
+                .iter()
+                .zip(vars.iter())
+                .map(|(coef, var)| max(0, coef * (ranges[*var].1 - ranges[*var].0)))
+                .sum::<i64>()
+                > *rhs
+        });
+        for (idx, var) in vars.iter().enumerate() {
+            let mut coefs = vec![0; vars.len()];
+            coefs[idx] = 1;
+            constraints.push((coefs, ranges[*var].1 - ranges[*var].0));
+        }
+        let costs: Vec<i64> = vars.iter().map(|var| system.free_costs[*var]).collect();
+        let (min_cost, values) = simplex_min(&constraints, &costs)?;
+        let fixed_total = system.pivot_total()
+            + ranges
+                .iter()
+                .zip(system.free_costs.iter())
+                .map(|(range, cost)| cost * range.0)
+                .sum::<i64>();
+        let lowest_total = (min_cost + Ratio::from(fixed_total as i128)) / (system.denom as i128);
+        let mut relaxed: Vec<Ratio<i128>> = ranges
+            .iter()
+            .map(|range| Ratio::from(range.0 as i128))
+            .collect();
+        for (var, value) in vars.iter().zip(values) {
+            relaxed[*var] += value;
+        }
+        Some((lowest_total.ceil().to_integer() as i64, relaxed))
+    }
+}
+
+// Finds the lowest `costs · x` over x >= 0 with `coefs · x <= rhs` for each
+// constraint, exactly, by the two-phase simplex method (choosing pivots by
+// Bland's rule, so it can't cycle). The constraints must bound every x.
+// Returns the lowest cost and the x with it, or None if no x meets them all
+fn simplex_min(
+    constraints: &[(Vec<i64>, i64)],
+    costs: &[i64],
+) -> Option<(Ratio<i128>, Vec<Ratio<i128>>)> {
+    let num_vars = costs.len();
+    let num_rows = constraints.len();
+    // Every row gets a slack column, and rows with a negative right-hand side
+    // (flipped so it's positive) get an artificial one to start the basis
+    let num_artificial = constraints.iter().filter(|(_, rhs)| *rhs < 0).count();
+    let num_cols = num_vars + num_rows + num_artificial;
+    let zero = Ratio::<i128>::zero();
+    let mut table: Vec<Vec<Ratio<i128>>> = vec![vec![zero; num_cols + 1]; num_rows];
+    let mut basis = vec![0; num_rows];
+    let mut artificial_col = num_vars + num_rows;
+    for (row_idx, (row, (coefs, rhs))) in table.iter_mut().zip(constraints.iter()).enumerate() {
+        let sign = if *rhs < 0 { -1 } else { 1 };
+        for (entry, coef) in row.iter_mut().zip(coefs.iter()) {
+            *entry = Ratio::from((sign * coef) as i128);
+        }
+        row[num_cols] = Ratio::from((sign * rhs) as i128);
+        let slack_col = num_vars + row_idx;
+        if *rhs < 0 {
+            row[slack_col] = Ratio::from(-1);
+            row[artificial_col] = Ratio::from(1);
+            basis[row_idx] = artificial_col;
+            artificial_col += 1;
+        } else {
+            row[slack_col] = Ratio::from(1);
+            basis[row_idx] = slack_col;
+        }
+    }
+    // Reduced costs (with the negated objective last): first for the sum of
+    // the artificial columns, then for the real costs
+    let mut objectives = vec![vec![zero; num_cols + 1]; 2];
+    for entry in objectives[0][num_vars + num_rows..num_cols].iter_mut() {
+        *entry = Ratio::from(1);
+    }
+    for (row, basic) in table.iter().zip(basis.iter()) {
+        if *basic >= num_vars + num_rows {
+            for (entry, row_entry) in objectives[0].iter_mut().zip(row.iter()) {
+                *entry -= row_entry;
+            }
+        }
+    }
+    for (entry, cost) in objectives[1].iter_mut().zip(costs.iter()) {
+        *entry = Ratio::from(*cost as i128);
+    }
+    let pivot = |table: &mut Vec<Vec<Ratio<i128>>>,
+                 objectives: &mut Vec<Vec<Ratio<i128>>>,
+                 pivot_row: usize,
+                 col: usize| {
+        let pivot = table[pivot_row][col];
+        table[pivot_row]
+            .iter_mut()
+            .for_each(|entry| *entry /= pivot);
+        let pivot_entries = table[pivot_row].clone();
+        for (row_idx, row) in table.iter_mut().chain(objectives.iter_mut()).enumerate() {
+            let factor = row[col];
+            if row_idx != pivot_row && !factor.is_zero() {
+                for (entry, pivot_entry) in row.iter_mut().zip(pivot_entries.iter()) {
+                    *entry -= factor * pivot_entry;
+                }
+            }
+        }
+    };
+    for (phase, allowed_cols) in [(0, num_cols), (1, num_vars + num_rows)] {
+        // Enter the first column that lowers the cost, leaving by the
+        // tightest ratio (ties to the lowest basic column)
+        while let Some(col) = (0..allowed_cols).find(|col| objectives[phase][*col].is_negative()) {
+            let pivot_row = (0..num_rows)
+                .filter(|row_idx| table[*row_idx][col].is_positive())
+                .min_by_key(|row_idx| {
+                    (
+                        table[*row_idx][num_cols] / table[*row_idx][col],
+                        basis[*row_idx],
+                    )
+                })?;
+            pivot(&mut table, &mut objectives, pivot_row, col);
+            basis[pivot_row] = col;
+        }
+        if phase == 0 {
+            if objectives[0][num_cols].is_negative() {
+                return None;
+            }
+            // Artificial columns still in the basis are at 0, so swap them
+            // for any real column in their row (if there's none, the row is
+            // redundant and never pivots again)
+            for row_idx in 0..num_rows {
+                if basis[row_idx] >= num_vars + num_rows
+                    && let Some(col) =
+                        (0..num_vars + num_rows).find(|col| !table[row_idx][*col].is_zero())
+                {
+                    pivot(&mut table, &mut objectives, row_idx, col);
+                    basis[row_idx] = col;
+                }
+            }
+        }
+    }
+    let mut values = vec![zero; num_vars];
+    for (row, basic) in table.iter().zip(basis.iter()) {
+        if *basic < num_vars {
+            values[*basic] = row[num_cols];
+        }
+    }
+    Some((-objectives[1][num_cols], values))
+}
+
+#[derive(Clone, Default)]
+struct Summary {
+    num_goal_presses: usize,
+    num_joltage_presses: usize,
+    errors: Vec<String>,
+    mismatches: Vec<String>,
+}
+
+impl Summary {
+    fn combine(mut self, other: Self) -> Self {
+        self.num_goal_presses += other.num_goal_presses;
+        self.num_joltage_presses += other.num_joltage_presses;
+        self.errors.extend(other.errors);
+        self.mismatches.extend(other.mismatches);
+        self
+    }
+}
+
+fn describe(result: &Result<usize, String>) -> String {
+    match result {
+        Ok(num_presses) => format!("{} presses", num_presses),
+        Err(e) => e.clone(),
     }
 }
 
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let machines: Vec<Machine> = FileReader::new(file_name.as_str())
-        .into_iter()
-        .map(|line| Machine::from(line))
-        .collect();
-    let num_goal_presses = machines
-        .iter()
-        .fold(0, |acc, element| acc + element.num_goal_presses());
+    let args = Args::new();
+    let file_name = args
+        .positional(0)
+        .expect("Usage: <binary> input.txt [--parallel] [--solver=exact|microlp|highs|lpsolve|cbc] [--cross-check=<solver>] (highs, lpsolve and cbc need the Cargo feature of the same name)");
+    let solver = args.value("solver").unwrap_or(Backend::Microlp).solver();
+    let cross_check = args.value("cross-check").map(Backend::solver);
+    let summary = FileReader::new(file_name).enumerate().map_reduce(
+        args.flag("parallel"),
+        |(idx, line)| {
+            let machine = Machine::from(line);
+            let mut summary = Summary {
+                num_goal_presses: machine.num_goal_presses(),
+                ..Default::default()
+            };
+            let result = machine.num_joltage_presses(solver.as_ref());
+            if let Some(other_solver) = cross_check.as_ref() {
+                // Both failing counts as agreeing, even for different reasons
+                let other_result = machine.num_joltage_presses(other_solver.as_ref());
+                if result.as_ref().ok() != other_result.as_ref().ok() {
+                    summary.mismatches.push(format!(
+                        "Machine {}: {} gives {}, but {} gives {}",
+                        idx + 1,
+                        solver.name(),
+                        describe(&result),
+                        other_solver.name(),
+                        describe(&other_result)
+                    ));
+                }
+            }
+            match result {
+                Ok(num_presses) => summary.num_joltage_presses = num_presses,
+                Err(e) => summary.errors.push(format!("Machine {}: {}", idx + 1, e)),
+            }
+            summary
+        },
+        Summary::default(),
+        Summary::combine,
+    );
     println!(
         "[Part1] Toggling all indicator lights took {} presses",
-        num_goal_presses
+        summary.num_goal_presses
     );
-    let num_joltage_presses = machines
-        .iter()
-        .fold(0, |acc, element| acc + element.num_joltage_presses());
+    for e in summary.errors.iter() {
+        eprintln!("{}", e);
+    }
+    if !summary.errors.is_empty() {
+        eprintln!(
+            "Skipping {} machine(s) whose joltages can't be met",
+            summary.errors.len()
+        );
+    }
     println!(
         "[Part2] Raising all joltages took {} presses",
-        num_joltage_presses
+        summary.num_joltage_presses
     );
+    if let Some(other_solver) = cross_check {
+        for mismatch in summary.mismatches.iter() {
+            println!("[Mismatch] {}", mismatch);
+        }
+        println!(
+            "[Cross-check] {} and {} disagree on {} machine(s)",
+            solver.name(),
+            other_solver.name(),
+            summary.mismatches.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tries every combination of presses within bounds
+    fn brute_force(machine: &Machine) -> Option<usize> {
+        let bounds = machine.press_bounds();
+        let mut presses = vec![0; bounds.len()];
+        let mut fewest = None;
+        loop {
+            if machine.meets_joltage_reqs(&presses) {
+                let total = presses.iter().sum();
+                fewest = Some(fewest.map_or(total, |fewest| min(fewest, total)));
+            }
+            // Count up, with each button's presses as a digit up to its bound
+            let Some(idx) = (0..presses.len()).find(|idx| presses[*idx] < bounds[*idx] as usize)
+            else {
+                return fewest;
+            };
+            presses[idx] += 1;
+            presses[..idx].fill(0);
+        }
+    }
+
+    #[test]
+    fn exact_matches_brute_force() {
+        // A fixed linear congruential generator, so every run checks the
+        // same machines
+        let mut seed: u64 = 1;
+        let mut random = |limit: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % limit
+        };
+        for _ in 0..500 {
+            let num_counters = 1 + random(4);
+            let buttons: Vec<Vec<usize>> = (0..1 + random(6))
+                .map(|_| (0..num_counters).filter(|_| random(2) == 1).collect())
+                .collect();
+            // Requirements from random presses can be met, but bumping one
+            // might make them impossible
+            let mut joltage_reqs = vec![0; num_counters];
+            for button in buttons.iter() {
+                let num_presses = random(3);
+                for idx in button {
+                    joltage_reqs[*idx] += num_presses;
+                }
+            }
+            if random(4) == 0 {
+                joltage_reqs[random(num_counters)] += 1;
+            }
+            let machine = Machine {
+                goal: vec![],
+                buttons,
+                joltage_reqs,
+            };
+            let presses = ExactSolver.presses(&machine);
+            if let Ok(presses) = presses.as_ref() {
+                assert!(machine.meets_joltage_reqs(presses), "{:?}", machine);
+            }
+            assert_eq!(
+                presses.ok().map(|presses| presses.iter().sum()),
+                brute_force(&machine),
+                "{:?}",
+                machine
+            );
+        }
+    }
 }
@@ -1,58 +1,111 @@
-use utils::FileReader;
+use num_bigint::BigUint;
+use utils::{Args, FileReader, MapReduce};
 
-trait MaxJoltage {
-    fn get_max_joltage(&self, num_digits: u32) -> u64;
+// The largest joltage a bank can produce, and the indices of the batteries
+// turned on to produce it
+struct Joltage {
+    value: BigUint,
+    indices: Vec<usize>,
 }
 
-fn get_num(bank: &str, num_to_get: u32, min_idx: usize, max_idx: usize) -> Option<usize> {
-    // Gets the index of the number at least min_idx into bank, at most max_idx, if present
-    let char = std::char::from_digit(num_to_get, 10).unwrap();
-    bank.chars()
-        .enumerate()
-        .position(|(idx, c)| (c == char) & (idx >= min_idx) & (idx <= max_idx))
+trait MaxJoltage {
+    fn get_max_joltage(&self, num_digits: usize) -> Option<Joltage>;
 }
 
-fn get_max_joltage_helper(bank: &str, num_digits_required: u32, min_idx: usize) -> Option<u64> {
-    if num_digits_required == 0 {
-        return Some(0);
-    }
-    for i in (0..=9).rev() {
-        let largest_num_idx = get_num(
-            bank,
-            i,
-            min_idx,
-            bank.len() - (num_digits_required as usize),
-        );
-        match largest_num_idx {
-            None => continue,
-            Some(idx) => match get_max_joltage_helper(bank, num_digits_required - 1, idx + 1) {
-                None => continue,
-                Some(lesser_num) => {
-                    let coeff: u64 = 10_u64.pow(num_digits_required - 1);
-                    return Some(((i as u64) * coeff) + lesser_num);
-                }
-            },
+impl MaxJoltage for &str {
+    // Greedily keeps a stack of chosen batteries, popping smaller ones
+    // whenever a larger one comes along and we can still afford to skip
+    // batteries. None if the bank has too few batteries (or isn't all digits)
+    fn get_max_joltage(&self, num_digits: usize) -> Option<Joltage> {
+        let bank = self
+            .chars()
+            .map(|c| c.to_digit(10).map(|digit| digit as u8))
+            .collect::<Option<Vec<u8>>>()?;
+        let mut num_to_skip = bank.len().checked_sub(num_digits)?;
+        let mut indices: Vec<usize> = Vec::with_capacity(bank.len());
+        for (idx, digit) in bank.iter().enumerate() {
+            while num_to_skip > 0 && indices.last().is_some_and(|top| bank[*top] < *digit) {
+                indices.pop();
+                num_to_skip -= 1;
+            }
+            indices.push(idx);
         }
+        indices.truncate(num_digits);
+        let digits: Vec<u8> = indices.iter().map(|idx| bank[*idx]).collect();
+        let value = BigUint::from_radix_be(&digits, 10)?;
+        Some(Joltage { value, indices })
     }
-    None
 }
 
-impl MaxJoltage for &str {
-    fn get_max_joltage(&self, num_digits: u32) -> u64 {
-        get_max_joltage_helper(&self, num_digits, 0).unwrap()
+// Marks the chosen batteries with a `^` beneath the bank
+fn show_joltage(bank: &str, joltage: &Joltage) {
+    let mut markers = vec![' '; bank.len()];
+    for idx in joltage.indices.iter() {
+        markers[*idx] = '^';
+    }
+    println!("{}", bank);
+    println!(
+        "{} ({} batteries: {})",
+        markers.into_iter().collect::<String>(),
+        joltage.indices.len(),
+        joltage.value
+    );
+}
+
+fn bank_joltage(bank: &str, line_num: usize, num_digits: usize, show: bool) -> BigUint {
+    match bank.get_max_joltage(num_digits) {
+        Some(joltage) => {
+            if show {
+                show_joltage(bank, &joltage);
+            }
+            joltage.value
+        }
+        None => {
+            eprintln!(
+                "Line {}: can't turn on {} batteries in bank \"{}\"",
+                line_num, num_digits, bank
+            );
+            BigUint::default()
+        }
     }
 }
 
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let (part1_joltage, part2_joltage) = FileReader::new(file_name.as_str())
-        .map(|line| {
-            let line_str = line.as_str();
-            (line_str.get_max_joltage(2), line_str.get_max_joltage(12))
-        })
-        .fold((0, 0), |acc, element| {
-            (acc.0 + element.0, acc.1 + element.1)
-        });
-    println!("[Part1] Max joltage of all banks is {}", part1_joltage);
-    println!("[Part2] Max joltage of all banks is {}", part2_joltage);
+    let args = Args::new();
+    let file_name = args
+        .positional(0)
+        .expect("Usage: <binary> input.txt [--digits=<num>] [--show] [--parallel]");
+    let custom_digits: Option<usize> = args.value("digits");
+    let digit_counts = match custom_digits {
+        None => vec![2, 12],
+        Some(num_digits) => vec![num_digits],
+    };
+    // Show banks in order, rather than as threads finish them
+    let show = args.flag("show");
+    let joltages: Vec<BigUint> = FileReader::new(file_name).enumerate().map_reduce(
+        args.flag("parallel") && !show,
+        |(idx, line)| {
+            digit_counts
+                .iter()
+                .map(|num_digits| bank_joltage(line.as_str(), idx + 1, *num_digits, show))
+                .collect()
+        },
+        vec![BigUint::default(); digit_counts.len()],
+        |acc, element| {
+            acc.into_iter()
+                .zip(element)
+                .map(|(total, joltage)| total + joltage)
+                .collect()
+        },
+    );
+    match custom_digits {
+        None => {
+            println!("[Part1] Max joltage of all banks is {}", joltages[0]);
+            println!("[Part2] Max joltage of all banks is {}", joltages[1]);
+        }
+        Some(num_digits) => println!(
+            "[Custom] Max joltage of all banks with {} batteries is {}",
+            num_digits, joltages[0]
+        ),
+    }
 }
@@ -1,22 +1,80 @@
-use utils::FileReader;
+use std::str::FromStr;
+use utils::{Args, FileReader};
 
+#[derive(Clone, Copy)]
+enum Neighborhood {
+    // All 8 surrounding cells
+    Moore,
+    // Only the 4 orthogonally adjacent cells
+    VonNeumann,
+}
+
+impl FromStr for Neighborhood {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "moore" => Ok(Neighborhood::Moore),
+            "von-neumann" => Ok(Neighborhood::VonNeumann),
+            _ => Err(format!("Invalid neighborhood: {}", s)),
+        }
+    }
+}
+
+impl Neighborhood {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Neighborhood::Moore => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Neighborhood::VonNeumann => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+        }
+    }
+}
+
+// A cell is accessible if it's occupied and fewer than `threshold` of its
+// neighbors are occupied
+struct Rules {
+    neighborhood: Neighborhood,
+    occupied: char,
+    threshold: usize,
+    // Whether neighbors wrap around the edges of the grid
+    wrap: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            neighborhood: Neighborhood::Moore,
+            occupied: '@',
+            threshold: 4,
+            wrap: false,
+        }
+    }
+}
+
+#[derive(Default)]
 struct Grid {
     chars: Vec<Vec<char>>,
+    rules: Rules,
     pub x: usize,
     pub y: usize,
 }
 
-impl Default for Grid {
-    fn default() -> Self {
+impl Grid {
+    fn new(rules: Rules) -> Self {
         Self {
-            chars: vec![],
-            x: 0,
-            y: 0,
+            rules,
+            ..Default::default()
         }
     }
-}
 
-impl Grid {
     fn add_line(&mut self, line: String) {
         let new_char_vec: Vec<char> = line.chars().collect();
         if self.x == 0 {
@@ -26,89 +84,195 @@ impl Grid {
         self.chars.push(new_char_vec);
     }
 
-    fn can_access(&self, x: i32, y: i32) -> bool {
-        let coords_to_check = vec![
-            (x - 1, y - 1),
-            (x, y - 1),
-            (x + 1, y - 1),
-            (x - 1, y),
-            (x + 1, y),
-            (x - 1, y + 1),
-            (x, y + 1),
-            (x + 1, y + 1),
-        ]
-        .into_iter();
-        let map = coords_to_check
-            .map(|(curr_x, curr_y)| {
-                if curr_x < 0 {
-                    0
-                } else if curr_y < 0 {
-                    0
-                } else if (curr_x, curr_y) == (x, y) {
-                    0
+    fn is_roll(&self, x: usize, y: usize) -> bool {
+        self.chars[y].get(x) == Some(&self.rules.occupied)
+    }
+
+    fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.rules
+            .neighborhood
+            .offsets()
+            .iter()
+            .filter_map(move |(dx, dy)| {
+                if self.rules.wrap {
+                    let new_x = (x as isize + dx).rem_euclid(self.x as isize) as usize;
+                    let new_y = (y as isize + dy).rem_euclid(self.y as isize) as usize;
+                    // Small grids can wrap back around to the cell itself
+                    ((new_x, new_y) != (x, y)).then_some((new_x, new_y))
                 } else {
-                    match self.chars.get(curr_y as usize) {
-                        None => 0,
-                        Some(inner_vec) => match inner_vec.get(curr_x as usize) {
-                            None => 0,
-                            Some(char) => (*char == '@') as i32,
-                        },
-                    }
+                    let new_x = x.checked_add_signed(*dx)?;
+                    let new_y = y.checked_add_signed(*dy)?;
+                    ((new_x < self.x) && (new_y < self.y)).then_some((new_x, new_y))
                 }
             })
-            .fold(0, |acc, element| acc + element);
-        map < 4
     }
 
-    fn is_accessable(&self) -> Vec<Vec<bool>> {
-        self.chars
-            .iter()
-            .enumerate()
-            .map(|(y, row)| {
-                row.iter()
-                    .enumerate()
-                    .map(|(x, c)| (*c == '@') & self.can_access(x as i32, y as i32))
+    // Peels off accessible rolls in waves; wave 1 is every roll accessible
+    // at the start, wave 2 is every roll accessible once those are removed,
+    // and so on. Only the neighbors of removed rolls are re-examined, using
+    // a running count of each roll's neighboring rolls. Returns the wave
+    // each roll was removed in, if it ever is
+    fn removal_waves(&self) -> Vec<Vec<Option<usize>>> {
+        let mut waves: Vec<Vec<Option<usize>>> = vec![vec![None; self.x]; self.y];
+        let mut num_neighbors: Vec<Vec<usize>> = vec![vec![0; self.x]; self.y];
+        let mut to_remove: Vec<(usize, usize)> = vec![];
+        for y in 0..self.y {
+            for x in (0..self.x).filter(|x| self.is_roll(*x, y)) {
+                num_neighbors[y][x] = self
+                    .neighbors(x, y)
+                    .filter(|(neighbor_x, neighbor_y)| self.is_roll(*neighbor_x, *neighbor_y))
+                    .count();
+                if num_neighbors[y][x] < self.rules.threshold {
+                    waves[y][x] = Some(1);
+                    to_remove.push((x, y));
+                }
+            }
+        }
+        let mut wave = 1;
+        while !to_remove.is_empty() {
+            let mut next_to_remove: Vec<(usize, usize)> = vec![];
+            for (x, y) in to_remove {
+                for (neighbor_x, neighbor_y) in self.neighbors(x, y) {
+                    if !self.is_roll(neighbor_x, neighbor_y)
+                        || waves[neighbor_y][neighbor_x].is_some()
+                    {
+                        continue;
+                    }
+                    num_neighbors[neighbor_y][neighbor_x] -= 1;
+                    if num_neighbors[neighbor_y][neighbor_x] < self.rules.threshold {
+                        waves[neighbor_y][neighbor_x] = Some(wave + 1);
+                        next_to_remove.push((neighbor_x, neighbor_y));
+                    }
+                }
+            }
+            to_remove = next_to_remove;
+            wave += 1;
+        }
+        waves
+    }
+
+    fn cell_state(&self, waves: &[Vec<Option<usize>>], x: usize, y: usize, wave: usize) -> Cell {
+        match waves[y][x] {
+            Some(removed_wave) if removed_wave < wave => Cell::Removed,
+            Some(removed_wave) if removed_wave == wave => Cell::JustRemoved,
+            _ if self.is_roll(x, y) => Cell::Roll(self.rules.occupied),
+            _ => Cell::Empty(self.chars[y].get(x).copied().unwrap_or(' ')),
+        }
+    }
+
+    // The grid once the given wave has been removed (wave 0 is the start)
+    fn frame(&self, waves: &[Vec<Option<usize>>], wave: usize) -> Vec<Vec<Cell>> {
+        (0..self.y)
+            .map(|y| {
+                (0..self.x)
+                    .map(|x| self.cell_state(waves, x, y, wave))
                     .collect()
             })
             .collect()
     }
+}
 
-    fn num_accessable(&self) -> u64 {
-        self.is_accessable()
-            .into_iter()
-            .flatten()
-            .filter(|b| *b)
-            .count() as u64
+enum Cell {
+    Empty(char),
+    Roll(char),
+    JustRemoved,
+    Removed,
+}
+
+impl Cell {
+    fn to_text(&self, color: bool) -> String {
+        match (self, color) {
+            (Cell::Empty(c), _) => c.to_string(),
+            (Cell::Roll(c), false) => c.to_string(),
+            (Cell::Roll(c), true) => format!("\x1b[32m{}\x1b[0m", c),
+            (Cell::JustRemoved | Cell::Removed, false) => "X".to_string(),
+            (Cell::JustRemoved, true) => "\x1b[1;31mX\x1b[0m".to_string(),
+            (Cell::Removed, true) => "\x1b[90mX\x1b[0m".to_string(),
+        }
     }
 
-    fn update_accessable(&mut self) -> u64 {
-        let accessable_grid = self.is_accessable();
-        let num_to_remove = accessable_grid.iter().flatten().filter(|b| **b).count() as u64;
-        for (char_row, bool_row) in self.chars.iter_mut().zip(accessable_grid) {
-            for (char, bool) in char_row.iter_mut().zip(bool_row) {
-                if bool {
-                    *char = 'X'
-                }
-            }
+    fn to_rgb(&self) -> [u8; 3] {
+        match self {
+            Cell::Empty(_) => [240, 240, 240],
+            Cell::Roll(_) => [30, 120, 50],
+            Cell::JustRemoved => [220, 40, 40],
+            Cell::Removed => [180, 180, 180],
+        }
+    }
+}
+
+fn print_frame(frame: &[Vec<Cell>], wave: usize, color: bool) {
+    println!("[Wave {}]", wave);
+    for row in frame {
+        println!(
+            "{}",
+            row.iter()
+                .map(|cell| cell.to_text(color))
+                .collect::<String>()
+        );
+    }
+    println!();
+}
+
+// Each cell is drawn as a square of PPM_CELL_SIZE x PPM_CELL_SIZE pixels
+const PPM_CELL_SIZE: usize = 4;
+
+fn write_ppm(frame: &[Vec<Cell>], path: &str) -> std::io::Result<()> {
+    let height = frame.len() * PPM_CELL_SIZE;
+    let width = frame.first().map_or(0, |row| row.len()) * PPM_CELL_SIZE;
+    let mut image = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for row in frame {
+        let pixel_row: Vec<u8> = row
+            .iter()
+            .flat_map(|cell| cell.to_rgb().repeat(PPM_CELL_SIZE))
+            .collect();
+        for _ in 0..PPM_CELL_SIZE {
+            image.extend_from_slice(&pixel_row);
         }
-        num_to_remove
     }
+    std::fs::write(path, image)
 }
 
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let mut grid = FileReader::new(file_name.as_str()).fold(Grid::default(), |mut grid, line| {
+    let args = Args::new();
+    let file_name = args.positional(0).expect(
+        "Usage: <binary> input.txt [--neighborhood=moore|von-neumann] [--symbol=<char>] [--threshold=<num>] [--wrap] [--frames] [--color] [--ppm=<prefix>]",
+    );
+    let default_rules = Rules::default();
+    let rules = Rules {
+        neighborhood: args
+            .value("neighborhood")
+            .unwrap_or(default_rules.neighborhood),
+        occupied: args.value("symbol").unwrap_or(default_rules.occupied),
+        threshold: args.value("threshold").unwrap_or(default_rules.threshold),
+        wrap: args.flag("wrap"),
+    };
+    let grid = FileReader::new(file_name).fold(Grid::new(rules), |mut grid, line| {
         grid.add_line(line);
         grid
     });
-    println!("[Part1] {} rolls are accessible", grid.num_accessable());
-    let mut total_removed = 0;
-    loop {
-        let more_removed = grid.update_accessable();
-        total_removed += more_removed;
-        if more_removed == 0 {
-            break;
+    let waves = grid.removal_waves();
+    let num_accessible = waves
+        .iter()
+        .flatten()
+        .filter(|wave| **wave == Some(1))
+        .count();
+    println!("[Part1] {} rolls are accessible", num_accessible);
+    let total_removed = waves.iter().flatten().filter(|wave| wave.is_some()).count();
+    println!("[Part2] {} rolls are accessible", total_removed);
+    let ppm_prefix: Option<String> = args.value("ppm");
+    if args.flag("frames") || ppm_prefix.is_some() {
+        let num_waves = waves.iter().flatten().flatten().max().copied().unwrap_or(0);
+        for wave in 0..=num_waves {
+            let frame = grid.frame(&waves, wave);
+            if args.flag("frames") {
+                print_frame(&frame, wave, args.flag("color"));
+            }
+            if let Some(prefix) = ppm_prefix.as_ref() {
+                let path = format!("{}_{:03}.ppm", prefix, wave);
+                write_ppm(&frame, path.as_str())
+                    .unwrap_or_else(|e| panic!("Failed to write {}: {}", path, e));
+            }
         }
     }
-    println!("[Part2] {} rolls are accessible", total_removed);
 }
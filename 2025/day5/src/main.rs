@@ -1,6 +1,7 @@
-use std::cmp::max;
+use std::cmp::{max, min};
+use std::io::{BufRead, BufWriter, Write};
 use std::ops::RangeInclusive;
-use utils::FileReader;
+use utils::{Args, FileReader};
 
 type IngredientID = u64;
 
@@ -15,7 +16,7 @@ struct FreshRanges {
 
 impl FreshRanges {
     fn new(ranges: Vec<RangeInclusive<IngredientID>>) -> Self {
-        Self { ranges: ranges }
+        Self { ranges }
     }
     fn is_fresh(&self, id: IngredientID) -> bool {
         self.ranges.iter().any(|range| range.contains(&id))
@@ -55,6 +56,104 @@ impl FreshRanges {
     }
 }
 
+// Sorted, disjoint ranges for fast lookups, along with the number of IDs
+// in all ranges before each one
+struct FreshIndex {
+    starts: Vec<IngredientID>,
+    ends: Vec<IngredientID>,
+    num_before: Vec<u64>,
+}
+
+impl From<&FreshRanges> for FreshIndex {
+    fn from(fresh_ranges: &FreshRanges) -> Self {
+        let mut ranges = fresh_ranges.ranges.clone();
+        ranges.sort_by_key(|range| *range.start());
+        let mut index = Self {
+            starts: vec![],
+            ends: vec![],
+            num_before: vec![],
+        };
+        let mut num_before = 0;
+        for range in ranges.into_iter().filter(|range| !range.is_empty()) {
+            match index.ends.last_mut() {
+                // Merge overlapping or adjacent ranges
+                Some(end) if *range.start() <= end.saturating_add(1) => {
+                    num_before += range.end().saturating_sub(*end);
+                    *end = max(*end, *range.end());
+                }
+                _ => {
+                    index.starts.push(*range.start());
+                    index.ends.push(*range.end());
+                    index.num_before.push(num_before);
+                    num_before += num_elements(range);
+                }
+            }
+        }
+        index
+    }
+}
+
+impl FreshIndex {
+    // The number of fresh IDs that are at most `id`
+    fn num_fresh_up_to(&self, id: IngredientID) -> u64 {
+        match self.starts.partition_point(|start| *start <= id) {
+            0 => 0,
+            idx => {
+                self.num_before[idx - 1] + min(id, self.ends[idx - 1]) - self.starts[idx - 1] + 1
+            }
+        }
+    }
+
+    fn is_fresh(&self, id: IngredientID) -> bool {
+        match self.starts.partition_point(|start| *start <= id) {
+            0 => false,
+            idx => id <= self.ends[idx - 1],
+        }
+    }
+
+    fn num_fresh(&self, range: RangeInclusive<IngredientID>) -> u64 {
+        if range.is_empty() {
+            return 0;
+        }
+        self.num_fresh_up_to(*range.end())
+            - range
+                .start()
+                .checked_sub(1)
+                .map_or(0, |before| self.num_fresh_up_to(before))
+    }
+}
+
+// Answers one query: either an ID ("5") for whether it's fresh, or a range
+// ("3-17") for how many IDs in it are fresh
+fn answer_query(index: &FreshIndex, query: &str) -> Result<String, String> {
+    let parse_id = |id: &str| {
+        id.trim()
+            .parse::<IngredientID>()
+            .map_err(|e| format!("Invalid ID \"{}\" ({})", id, e))
+    };
+    match query.split_once("-") {
+        None => Ok(if index.is_fresh(parse_id(query)?) {
+            "fresh".to_string()
+        } else {
+            "spoiled".to_string()
+        }),
+        Some((start, end)) => Ok(index
+            .num_fresh(RangeInclusive::new(parse_id(start)?, parse_id(end)?))
+            .to_string()),
+    }
+}
+
+// Streams queries from stdin, one per line, answering each on stdout
+fn serve(index: &FreshIndex) {
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    for query in std::io::stdin().lock().lines().map_while(Result::ok) {
+        let answer =
+            answer_query(index, query.as_str()).unwrap_or_else(|e| format!("error: {}", e));
+        writeln!(out, "{}", answer).unwrap();
+    }
+    out.flush().unwrap();
+}
+
 fn parse_database(mut str_iter: impl Iterator<Item = String>) -> (FreshRanges, Vec<IngredientID>) {
     let ranges = str_iter
         .by_ref()
@@ -75,8 +174,15 @@ fn parse_database(mut str_iter: impl Iterator<Item = String>) -> (FreshRanges, V
 }
 
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let (mut fresh_ranges, ingredient_ids) = parse_database(FileReader::new(file_name.as_str()));
+    let args = Args::new();
+    let file_name = args
+        .positional(0)
+        .expect("Usage: <binary> input.txt [--serve]");
+    let (mut fresh_ranges, ingredient_ids) = parse_database(FileReader::new(file_name));
+    if args.flag("serve") {
+        serve(&FreshIndex::from(&fresh_ranges));
+        return;
+    }
     let num_fresh = ingredient_ids
         .into_iter()
         .filter(|id| fresh_ranges.is_fresh(*id))
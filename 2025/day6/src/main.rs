@@ -1,10 +1,19 @@
+use num_bigint::BigInt;
+use std::cmp::{max, min};
+use std::fmt;
+use std::ops::Range;
 use std::str::FromStr;
-use utils::FileReader;
+use utils::{Args, FileReader};
 
 #[derive(Debug)]
 enum Op {
     Multiply,
     Add,
+    Subtract,
+    Divide,
+    Min,
+    Max,
+    Concat,
 }
 
 impl FromStr for Op {
@@ -13,115 +22,333 @@ impl FromStr for Op {
         match s {
             "*" => Ok(Op::Multiply),
             "+" => Ok(Op::Add),
+            "-" => Ok(Op::Subtract),
+            "/" => Ok(Op::Divide),
+            "<" => Ok(Op::Min),
+            ">" => Ok(Op::Max),
+            "|" => Ok(Op::Concat),
             _ => Err(format!("Invalid operation: {}", s)),
         }
     }
 }
 
-impl Op {
-    fn init_val(&self) -> u64 {
-        match *self {
-            Op::Multiply => 1,
-            Op::Add => 0,
+// A number the worksheet can be computed with
+trait Value: Sized + fmt::Display {
+    fn parse_digits(digits: &str) -> Result<Self, String>;
+    fn zero() -> Self;
+    fn apply(op: &Op, arg1: Self, arg2: Self) -> Result<Self, String>;
+    fn add(arg1: Self, arg2: Self) -> Result<Self, String> {
+        Self::apply(&Op::Add, arg1, arg2)
+    }
+}
+
+impl Value for u64 {
+    fn parse_digits(digits: &str) -> Result<Self, String> {
+        digits
+            .parse()
+            .map_err(|e| format!("invalid number \"{}\" ({})", digits, e))
+    }
+    fn zero() -> Self {
+        0
+    }
+    fn apply(op: &Op, arg1: Self, arg2: Self) -> Result<Self, String> {
+        match *op {
+            Op::Multiply => arg1.checked_mul(arg2),
+            Op::Add => arg1.checked_add(arg2),
+            Op::Subtract => arg1.checked_sub(arg2),
+            Op::Divide if arg2 == 0 => return Err("division by zero".to_string()),
+            Op::Divide => Some(arg1 / arg2),
+            Op::Min => Some(min(arg1, arg2)),
+            Op::Max => Some(max(arg1, arg2)),
+            Op::Concat => 10_u64
+                .checked_pow(arg2.checked_ilog10().unwrap_or(0) + 1)
+                .and_then(|shift| arg1.checked_mul(shift))
+                .and_then(|shifted| shifted.checked_add(arg2)),
         }
+        .ok_or(format!("{:?} of {} and {} overflows", op, arg1, arg2))
+    }
+}
+
+impl Value for BigInt {
+    fn parse_digits(digits: &str) -> Result<Self, String> {
+        digits
+            .parse()
+            .map_err(|e| format!("invalid number \"{}\" ({})", digits, e))
     }
-    fn apply(&self, arg1: u64, arg2: u64) -> u64 {
-        match *self {
-            Op::Multiply => arg1 * arg2,
-            Op::Add => arg1 + arg2,
+    fn zero() -> Self {
+        BigInt::ZERO
+    }
+    fn apply(op: &Op, arg1: Self, arg2: Self) -> Result<Self, String> {
+        match *op {
+            Op::Multiply => Ok(arg1 * arg2),
+            Op::Add => Ok(arg1 + arg2),
+            Op::Subtract => Ok(arg1 - arg2),
+            Op::Divide if arg2 == BigInt::ZERO => Err("division by zero".to_string()),
+            Op::Divide => Ok(arg1 / arg2),
+            Op::Min => Ok(min(arg1, arg2)),
+            Op::Max => Ok(max(arg1, arg2)),
+            Op::Concat => Self::parse_digits(format!("{}{}", arg1, arg2).as_str()),
         }
     }
 }
 
-fn grand_total(str_iter: impl Iterator<Item = String>) -> u64 {
-    let mut str_vecs: Vec<Vec<String>> = str_iter
-        .map(|line| line.split_whitespace().map(|str| str.to_string()).collect())
-        .collect();
-    let op_vec: Vec<Op> = str_vecs
-        .pop()
-        .unwrap()
-        .into_iter()
-        .map(|str| str.parse().unwrap())
-        .collect();
-    let arg_vecs: Vec<Vec<u64>> = str_vecs
-        .into_iter()
-        .map(|arg_vec| {
-            arg_vec
-                .into_iter()
-                .map(|str| str.parse().unwrap())
-                .collect()
-        })
-        .collect();
-    let init_vals = op_vec.iter().map(|op| op.init_val()).collect::<Vec<u64>>();
-    let result_vec = arg_vecs.iter().fold(init_vals, |curr_vals, new_args| {
-        curr_vals
-            .into_iter()
-            .enumerate()
-            .map(|(idx, element)| op_vec[idx].apply(element, new_args[idx]))
-            .collect()
-    });
-    result_vec.into_iter().fold(0, |acc, element| acc + element)
+// Where a worksheet went wrong; lines and columns are 1-indexed
+#[derive(Debug)]
+struct WorksheetError {
+    line: usize,
+    col: usize,
+    reason: String,
+}
+
+impl fmt::Display for WorksheetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.col, self.reason
+        )
+    }
+}
+
+// How to read the numbers out of a problem's block of columns
+#[derive(Clone, Copy, Debug)]
+enum Reading {
+    // Each row is a number
+    Rows,
+    // Each column is a number read top to bottom, with the columns taken
+    // from right to left
+    ColumnsRightToLeft,
+    ColumnsLeftToRight,
 }
 
-fn grand_cephalopod_total(str_iter: impl Iterator<Item = String>) -> u64 {
-    let mut str_vecs: Vec<String> = str_iter.collect();
-    let num_cols = str_vecs[0].len();
-    let op_vec: Vec<Op> = str_vecs
-        .pop()
-        .unwrap()
-        .split_whitespace()
-        .into_iter()
-        .map(|str| str.parse().unwrap())
-        .collect();
-    let column_strs = str_vecs
-        .into_iter()
-        .map(|line| line.chars().collect::<Vec<char>>())
-        .fold(vec!["".to_string(); num_cols], |acc_vec, new_char_vec| {
-            acc_vec
-                .into_iter()
-                .zip(new_char_vec)
-                .map(|(mut curr_str, new_char)| {
-                    curr_str.push(new_char);
-                    curr_str
-                })
-                .collect()
-        });
-    let mut arg_vecs: Vec<Vec<u64>> = vec![];
-    let last_arg_vec = column_strs
-        .into_iter()
-        .fold(vec![], |mut curr_args, new_arg| match new_arg.trim() {
-            "" => {
-                arg_vecs.push(curr_args);
-                vec![]
+impl FromStr for Reading {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rows" => Ok(Reading::Rows),
+            "columns-rtl" => Ok(Reading::ColumnsRightToLeft),
+            "columns-ltr" => Ok(Reading::ColumnsLeftToRight),
+            _ => Err(format!("Invalid reading: {}", s)),
+        }
+    }
+}
+
+// A block of columns holding one problem
+struct Problem {
+    cols: Range<usize>,
+    op: Op,
+}
+
+// The number rows of a worksheet, padded to the same width, split into
+// problems separated by columns of only spaces
+struct Worksheet {
+    rows: Vec<Vec<char>>,
+    problems: Vec<Problem>,
+}
+
+impl Worksheet {
+    fn parse(str_iter: impl Iterator<Item = String>) -> Result<Self, WorksheetError> {
+        let mut rows: Vec<Vec<char>> = str_iter.map(|line| line.chars().collect()).collect();
+        let op_line = rows.len();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in rows.iter_mut() {
+            row.resize(width, ' ');
+        }
+        let op_row = rows.pop().ok_or(WorksheetError {
+            line: 1,
+            col: 1,
+            reason: "empty worksheet".to_string(),
+        })?;
+        for (line_idx, row) in rows.iter().enumerate() {
+            if let Some(col) = row.iter().position(|c| !c.is_ascii_digit() && *c != ' ') {
+                return Err(WorksheetError {
+                    line: line_idx + 1,
+                    col: col + 1,
+                    reason: format!("'{}' is not a digit", row[col]),
+                });
             }
-            new_arg => {
-                curr_args.push(new_arg.parse().unwrap());
-                curr_args
+        }
+        let is_blank_col =
+            |col: usize| (op_row[col] == ' ') && rows.iter().all(|row| row[col] == ' ');
+        let mut problems: Vec<Problem> = vec![];
+        let mut col = 0;
+        while col < width {
+            if is_blank_col(col) {
+                col += 1;
+                continue;
+            }
+            let start = col;
+            while (col < width) && !is_blank_col(col) {
+                col += 1;
             }
-        });
-    if !last_arg_vec.is_empty() {
-        arg_vecs.push(last_arg_vec);
-    }
-    arg_vecs
-        .into_iter()
-        .enumerate()
-        .map(|(idx, arg_vec)| {
-            let op = &op_vec[idx];
-            arg_vec
-                .into_iter()
-                .fold(op.init_val(), |acc, new_element| op.apply(acc, new_element))
-        })
-        .fold(0, |acc, new_element| acc + new_element)
+            problems.push(Problem {
+                cols: start..col,
+                op: Self::parse_op(&op_row, start..col, op_line)?,
+            });
+        }
+        Ok(Self { rows, problems })
+    }
+
+    // Each problem must have exactly one operator beneath it
+    fn parse_op(op_row: &[char], cols: Range<usize>, op_line: usize) -> Result<Op, WorksheetError> {
+        let mut op_cols = cols.clone().filter(|col| op_row[*col] != ' ');
+        let Some(op_col) = op_cols.next() else {
+            return Err(WorksheetError {
+                line: op_line,
+                col: cols.start + 1,
+                reason: format!("no operator for columns {}-{}", cols.start + 1, cols.end),
+            });
+        };
+        let op_end = (op_col..cols.end)
+            .find(|col| op_row[*col] == ' ')
+            .unwrap_or(cols.end);
+        if let Some(extra_col) = (op_end..cols.end).find(|col| op_row[*col] != ' ') {
+            return Err(WorksheetError {
+                line: op_line,
+                col: extra_col + 1,
+                reason: format!(
+                    "more than one operator for columns {}-{}",
+                    cols.start + 1,
+                    cols.end
+                ),
+            });
+        }
+        op_row[op_col..op_end]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|reason| WorksheetError {
+                line: op_line,
+                col: op_col + 1,
+                reason,
+            })
+    }
+
+    fn parse_num<V: Value>(digits: &str, line: usize, col: usize) -> Result<V, WorksheetError> {
+        V::parse_digits(digits).map_err(|reason| WorksheetError { line, col, reason })
+    }
+
+    // Applies the operator left to right across the problem's numbers
+    fn solve<V: Value>(&self, problem: &Problem, args: Vec<V>) -> Result<V, WorksheetError> {
+        let error = |reason: String| WorksheetError {
+            line: self.rows.len() + 1,
+            col: problem.cols.start + 1,
+            reason,
+        };
+        let mut args = args.into_iter();
+        let first_arg = args
+            .next()
+            .ok_or(error("no numbers in problem".to_string()))?;
+        args.try_fold(first_arg, |acc, arg| V::apply(&problem.op, acc, arg))
+            .map_err(error)
+    }
+
+    // Each row of the problem's block is a number
+    fn row_numbers<V: Value>(&self, problem: &Problem) -> Result<Vec<V>, WorksheetError> {
+        let mut nums: Vec<V> = vec![];
+        for (line_idx, row) in self.rows.iter().enumerate() {
+            let row_str: String = row[problem.cols.clone()].iter().collect();
+            let digits = row_str.trim();
+            let col = problem.cols.start + row_str.find(digits).unwrap_or(0) + 1;
+            if digits.is_empty() {
+                return Err(WorksheetError {
+                    line: line_idx + 1,
+                    col,
+                    reason: "missing number".to_string(),
+                });
+            }
+            nums.push(Self::parse_num(digits, line_idx + 1, col)?);
+        }
+        Ok(nums)
+    }
+
+    // Each column of the problem's block is a number, read top to bottom
+    fn column_numbers<V: Value>(&self, problem: &Problem) -> Result<Vec<V>, WorksheetError> {
+        let mut nums: Vec<V> = vec![];
+        for col in problem.cols.clone() {
+            let digits: String = self
+                .rows
+                .iter()
+                .map(|row| row[col])
+                .filter(|c| *c != ' ')
+                .collect();
+            // The number starts at the first row with a digit in this column
+            if let Some(first_row) = self.rows.iter().position(|row| row[col] != ' ') {
+                nums.push(Self::parse_num(digits.as_str(), first_row + 1, col + 1)?);
+            }
+        }
+        Ok(nums)
+    }
+
+    fn numbers<V: Value>(
+        &self,
+        problem: &Problem,
+        reading: Reading,
+    ) -> Result<Vec<V>, WorksheetError> {
+        match reading {
+            Reading::Rows => self.row_numbers(problem),
+            Reading::ColumnsRightToLeft => {
+                let mut nums = self.column_numbers(problem)?;
+                nums.reverse();
+                Ok(nums)
+            }
+            Reading::ColumnsLeftToRight => self.column_numbers(problem),
+        }
+    }
+
+    fn grand_total<V: Value>(&self, reading: Reading) -> Result<V, WorksheetError> {
+        let mut total = V::zero();
+        for problem in self.problems.iter() {
+            let solution = self.solve(problem, self.numbers(problem, reading)?)?;
+            total = V::add(total, solution).map_err(|reason| WorksheetError {
+                line: self.rows.len() + 1,
+                col: problem.cols.start + 1,
+                reason: format!("grand total overflows ({})", reason),
+            })?;
+        }
+        Ok(total)
+    }
+}
+
+fn print_totals<V: Value>(
+    worksheet: &Worksheet,
+    custom_reading: Option<Reading>,
+) -> Result<(), WorksheetError> {
+    match custom_reading {
+        None => {
+            println!(
+                "[Part1] The grand total is {}",
+                worksheet.grand_total::<V>(Reading::Rows)?
+            );
+            println!(
+                "[Part2] The grand total is {}",
+                worksheet.grand_total::<V>(Reading::ColumnsRightToLeft)?
+            );
+        }
+        Some(reading) => println!(
+            "[Custom] The grand total reading {:?} is {}",
+            reading,
+            worksheet.grand_total::<V>(reading)?
+        ),
+    }
+    Ok(())
 }
 
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    println!(
-        "[Part1] The grand total is {:?}",
-        grand_total(FileReader::new(file_name.as_str()))
-    );
-    println!(
-        "[Part2] The grand total is {:?}",
-        grand_cephalopod_total(FileReader::new(file_name.as_str()))
-    );
+    let args = Args::new();
+    let file_name = args
+        .positional(0)
+        .expect("Usage: <binary> input.txt [--reading=rows|columns-rtl|columns-ltr] [--bigint]");
+    let custom_reading: Option<Reading> = args.value("reading");
+    let result = Worksheet::parse(FileReader::new(file_name)).and_then(|worksheet| {
+        if args.flag("bigint") {
+            print_totals::<BigInt>(&worksheet, custom_reading)
+        } else {
+            print_totals::<u64>(&worksheet, custom_reading)
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Invalid worksheet at {}", e);
+        std::process::exit(1);
+    }
 }
@@ -1,56 +1,467 @@
-use std::collections::{HashMap, HashSet};
-use utils::FileReader;
+use num_bigint::BigUint;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Instant;
+use utils::{Args, FileReader};
 
-fn update_tachyons(tachyons: &mut HashMap<usize, u64>, loc: usize, count: u64) {
-    tachyons
-        .entry(loc)
-        .and_modify(|prev_count| *prev_count += count)
-        .or_insert(count);
+#[derive(Clone, Copy, PartialEq)]
+enum Cell {
+    Empty,
+    Start,
+    // Splits a beam into the columns on either side
+    Splitter,
+    // Absorbs a beam
+    Wall,
+    // Deflects a beam one column to the left ('/') or right ('\')
+    MirrorLeft,
+    MirrorRight,
 }
 
-fn process_manifold_string(
-    array: String,
-    tachyons: HashMap<usize, u64>,
+impl TryFrom<char> for Cell {
+    type Error = String;
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '.' => Ok(Cell::Empty),
+            'S' => Ok(Cell::Start),
+            '^' => Ok(Cell::Splitter),
+            '#' => Ok(Cell::Wall),
+            '/' => Ok(Cell::MirrorLeft),
+            '\\' => Ok(Cell::MirrorRight),
+            c => Err(format!("Invalid manifold cell: {}", c)),
+        }
+    }
+}
+
+// What happens to a beam that leaves the side of the manifold
+#[derive(Clone, Copy)]
+enum Boundary {
+    Drop,
+    Clamp,
+    Wrap,
+}
+
+impl FromStr for Boundary {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Boundary::Drop),
+            "clamp" => Ok(Boundary::Clamp),
+            "wrap" => Ok(Boundary::Wrap),
+            _ => Err(format!("Invalid boundary: {}", s)),
+        }
+    }
+}
+
+struct Manifold {
+    rows: Vec<Vec<Cell>>,
+    width: usize,
+    start: usize,
+    boundary: Boundary,
+}
+
+impl Manifold {
+    fn parse(mut lines: impl Iterator<Item = String>, boundary: Boundary) -> Result<Self, String> {
+        let first_line = lines.next().ok_or("Empty manifold")?;
+        let start = first_line
+            .chars()
+            .position(|c| c == 'S')
+            .ok_or("No start in the first row of the manifold")?;
+        let mut rows = lines
+            .enumerate()
+            .map(|(idx, line)| {
+                line.chars()
+                    .map(Cell::try_from)
+                    .collect::<Result<Vec<Cell>, String>>()
+                    .map_err(|e| format!("{} (line {})", e, idx + 2))
+            })
+            .collect::<Result<Vec<Vec<Cell>>, String>>()?;
+        let width = rows
+            .iter()
+            .map(|row| row.len())
+            .fold(first_line.len(), usize::max);
+        for row in rows.iter_mut() {
+            row.resize(width, Cell::Empty);
+        }
+        Ok(Self {
+            rows,
+            width,
+            start,
+            boundary,
+        })
+    }
+
+    // Where a beam moves to when shifted sideways, if it stays in the manifold
+    fn shift(&self, loc: usize, offset: isize) -> Option<usize> {
+        let new_loc = loc as isize + offset;
+        match self.boundary {
+            _ if (0..self.width as isize).contains(&new_loc) => Some(new_loc as usize),
+            Boundary::Drop => None,
+            Boundary::Clamp => Some(new_loc.clamp(0, self.width as isize - 1) as usize),
+            Boundary::Wrap => Some(new_loc.rem_euclid(self.width as isize) as usize),
+        }
+    }
+}
+
+// How timelines are counted; exactly (growing into a big integer if needed),
+// or modulo some number
+#[derive(Clone, Copy)]
+enum Counting {
+    Exact,
+    Modular(u64),
+}
+
+#[derive(Clone)]
+enum Count {
+    Small(u64),
+    Big(BigUint),
+}
+
+impl Count {
+    fn num_bits(&self) -> u64 {
+        match self {
+            Count::Small(count) => (u64::BITS - count.leading_zeros()) as u64,
+            Count::Big(count) => count.bits(),
+        }
+    }
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Count::Small(count) => write!(f, "{}", count),
+            Count::Big(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+impl Counting {
+    fn count(&self, count: u64) -> Count {
+        match *self {
+            Counting::Exact => Count::Small(count),
+            Counting::Modular(modulus) => Count::Small(count % modulus),
+        }
+    }
+
+    fn add(&self, count: &mut Count, other: &Count) {
+        match (*self, &mut *count, other) {
+            (Counting::Modular(modulus), Count::Small(prev), Count::Small(other)) => {
+                *prev = ((*prev as u128 + *other as u128) % modulus as u128) as u64;
+            }
+            (Counting::Modular(_), _, _) => unreachable!("Modular counts are never big"),
+            (Counting::Exact, Count::Small(prev), Count::Small(other)) => {
+                match prev.checked_add(*other) {
+                    Some(sum) => *prev = sum,
+                    None => *count = Count::Big(BigUint::from(*prev) + *other),
+                }
+            }
+            (Counting::Exact, Count::Small(prev), Count::Big(other)) => {
+                *count = Count::Big(other + *prev);
+            }
+            (Counting::Exact, Count::Big(prev), Count::Small(other)) => *prev += *other,
+            (Counting::Exact, Count::Big(prev), Count::Big(other)) => *prev += other,
+        }
+    }
+
+    fn sum<'a>(&self, counts: impl Iterator<Item = &'a Count>) -> Count {
+        counts.fold(self.count(0), |mut acc, count| {
+            self.add(&mut acc, count);
+            acc
+        })
+    }
+}
+
+// The beams on a row, mapping location -> count
+trait Tachyons {
+    fn new(width: usize) -> Self;
+    fn clear(&mut self);
+    fn get(&self, loc: usize) -> Option<&Count>;
+    fn beams(&self) -> impl Iterator<Item = (usize, &Count)>;
+    fn update(&mut self, loc: usize, count: &Count, counting: Counting);
+}
+
+struct SparseTachyons(HashMap<usize, Count>);
+
+impl Tachyons for SparseTachyons {
+    fn new(_width: usize) -> Self {
+        Self(HashMap::new())
+    }
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+    fn get(&self, loc: usize) -> Option<&Count> {
+        self.0.get(&loc)
+    }
+    fn beams(&self) -> impl Iterator<Item = (usize, &Count)> {
+        self.0.iter().map(|(loc, count)| (*loc, count))
+    }
+    fn update(&mut self, loc: usize, count: &Count, counting: Counting) {
+        self.0
+            .entry(loc)
+            .and_modify(|prev_count| counting.add(prev_count, count))
+            .or_insert_with(|| count.clone());
+    }
+}
+
+// One slot per column, so updates don't need to hash. Only the span of
+// columns between the outermost beams is walked or cleared
+struct DenseTachyons {
+    counts: Vec<Option<Count>>,
+    span: Range<usize>,
+}
+
+impl Tachyons for DenseTachyons {
+    fn new(width: usize) -> Self {
+        Self {
+            counts: vec![None; width],
+            span: 0..0,
+        }
+    }
+    fn clear(&mut self) {
+        self.counts[self.span.clone()].fill(None);
+        self.span = 0..0;
+    }
+    fn get(&self, loc: usize) -> Option<&Count> {
+        self.counts[loc].as_ref()
+    }
+    fn beams(&self) -> impl Iterator<Item = (usize, &Count)> {
+        self.counts[self.span.clone()]
+            .iter()
+            .zip(self.span.clone())
+            .filter_map(|(count, loc)| count.as_ref().map(|count| (loc, count)))
+    }
+    fn update(&mut self, loc: usize, count: &Count, counting: Counting) {
+        match self.counts[loc].as_mut() {
+            Some(prev_count) => counting.add(prev_count, count),
+            None => {
+                self.counts[loc] = Some(count.clone());
+                self.span = if self.span.is_empty() {
+                    loc..loc + 1
+                } else {
+                    min(self.span.start, loc)..max(self.span.end, loc + 1)
+                };
+            }
+        }
+    }
+}
+
+// Manifolds at least this wide use dense tachyon state by default
+const DENSE_WIDTH: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+enum Representation {
+    Sparse,
+    Dense,
+}
+
+impl FromStr for Representation {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sparse" => Ok(Representation::Sparse),
+            "dense" => Ok(Representation::Dense),
+            _ => Err(format!("Invalid state representation: {}", s)),
+        }
+    }
+}
+
+fn update_tachyons(
+    tachyons: &mut impl Tachyons,
+    loc: Option<usize>,
+    count: &Count,
+    counting: Counting,
+) {
+    if let Some(loc) = loc {
+        tachyons.update(loc, count, counting);
+    }
+}
+
+// Moves the beams in `tachyons` through the row, into `new_tachyons`
+fn process_manifold_row<T: Tachyons>(
+    manifold: &Manifold,
+    row: &[Cell],
+    tachyons: &T,
+    new_tachyons: &mut T,
+    counting: Counting,
     split_count: &mut usize,
-) -> HashMap<usize, u64> {
-    let splitter_locs: HashSet<usize> = array
-        .chars()
+) {
+    new_tachyons.clear();
+    for (loc, count) in tachyons.beams() {
+        match row[loc] {
+            Cell::Empty | Cell::Start => {
+                update_tachyons(new_tachyons, Some(loc), count, counting);
+            }
+            Cell::Splitter => {
+                *split_count += 1;
+                update_tachyons(new_tachyons, manifold.shift(loc, 1), count, counting);
+                update_tachyons(new_tachyons, manifold.shift(loc, -1), count, counting);
+            }
+            Cell::Wall => (),
+            Cell::MirrorLeft => {
+                update_tachyons(new_tachyons, manifold.shift(loc, -1), count, counting);
+            }
+            Cell::MirrorRight => {
+                update_tachyons(new_tachyons, manifold.shift(loc, 1), count, counting);
+            }
+        }
+    }
+}
+
+const HEATMAP_LEVELS: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+// Draws the row with `|` wherever a beam travels through an empty cell,
+// followed by a heatmap of the (log-scaled) timelines leaving each column
+fn render_row<T: Tachyons>(row: &[Cell], incoming: Option<&T>, outgoing: &T) -> String {
+    let cells: String = row
+        .iter()
         .enumerate()
-        .filter_map(|(idx, c)| if c == '^' { Some(idx) } else { None })
+        .map(|(loc, cell)| match cell {
+            Cell::Empty
+                if incoming.is_some_and(|tachyons| tachyons.get(loc).is_some())
+                    || outgoing.get(loc).is_some() =>
+            {
+                '|'
+            }
+            Cell::Empty => '.',
+            Cell::Start => 'S',
+            Cell::Splitter => '^',
+            Cell::Wall => '#',
+            Cell::MirrorLeft => '/',
+            Cell::MirrorRight => '\\',
+        })
         .collect();
-    let mut new_tachyons: HashMap<usize, u64> = HashMap::new();
-    for (loc, count) in tachyons {
-        if splitter_locs.contains(&loc) {
-            *split_count += 1;
-            update_tachyons(&mut new_tachyons, loc + 1, count);
-            update_tachyons(&mut new_tachyons, loc - 1, count);
-        } else {
-            update_tachyons(&mut new_tachyons, loc, count);
+    let max_bits = outgoing
+        .beams()
+        .map(|(_, count)| count.num_bits())
+        .max()
+        .unwrap_or(0);
+    let heatmap: String = (0..row.len())
+        .map(|loc| match outgoing.get(loc) {
+            None => HEATMAP_LEVELS[0],
+            Some(count) => {
+                let level = 1 + (count.num_bits() * 8) / max_bits.max(1);
+                HEATMAP_LEVELS[level.min(9) as usize]
+            }
+        })
+        .collect();
+    format!("{}  {}", cells, heatmap)
+}
+
+// Runs the beams through the manifold, returning the number of splits and
+// the number of timelines
+fn simulate<T: Tachyons>(
+    manifold: &Manifold,
+    counting: Counting,
+    show_rows: bool,
+    render: bool,
+) -> (usize, Count) {
+    let mut tachyons = T::new(manifold.width);
+    tachyons.update(manifold.start, &counting.count(1), counting);
+    if render {
+        let mut start_row = vec![Cell::Empty; manifold.width];
+        start_row[manifold.start] = Cell::Start;
+        println!("{}", render_row(&start_row, None, &tachyons));
+    }
+    let mut split_count: usize = 0;
+    // Double-buffered; each row reads from one state and writes to the other
+    let (final_state, _) = manifold.rows.iter().enumerate().fold(
+        (tachyons, T::new(manifold.width)),
+        |(tachyons, mut new_tachyons), (idx, row)| {
+            process_manifold_row(
+                manifold,
+                row,
+                &tachyons,
+                &mut new_tachyons,
+                counting,
+                &mut split_count,
+            );
+            if render {
+                println!("{}", render_row(row, Some(&tachyons), &new_tachyons));
+            }
+            if show_rows {
+                println!(
+                    "[Row {}] {} beams, {} timelines",
+                    idx + 2,
+                    new_tachyons.beams().count(),
+                    counting.sum(new_tachyons.beams().map(|(_, count)| count))
+                );
+            }
+            (new_tachyons, tachyons)
+        },
+    );
+    let timelines = counting.sum(final_state.beams().map(|(_, count)| count));
+    (split_count, timelines)
+}
+
+fn simulate_with(
+    representation: Representation,
+    manifold: &Manifold,
+    counting: Counting,
+    show_rows: bool,
+    render: bool,
+) -> (usize, Count) {
+    match representation {
+        Representation::Sparse => simulate::<SparseTachyons>(manifold, counting, show_rows, render),
+        Representation::Dense => simulate::<DenseTachyons>(manifold, counting, show_rows, render),
+    }
+}
+
+// Number of runs each representation is timed over for --bench
+const BENCH_RUNS: u32 = 10;
+
+fn bench(manifold: &Manifold, counting: Counting) {
+    for representation in [Representation::Sparse, Representation::Dense] {
+        let start = Instant::now();
+        for _ in 0..BENCH_RUNS {
+            simulate_with(representation, manifold, counting, false, false);
         }
+        println!(
+            "[Bench] {:?} state took {:?} per run",
+            representation,
+            start.elapsed() / BENCH_RUNS
+        );
     }
-    new_tachyons
 }
 
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let mut lines = FileReader::new(file_name.as_str()).into_iter();
-    // maps location -> count
-    let tachyons: HashMap<usize, u64> = HashMap::from([(
-        lines
-            .next()
-            .unwrap()
-            .chars()
-            .position(|c| c == 'S')
-            .unwrap(),
-        1,
-    )]);
-    let mut split_count: usize = 0;
-    let final_state = lines.fold(tachyons, |tachyons, line| {
-        process_manifold_string(line, tachyons, &mut split_count)
-    });
+    let args = Args::new();
+    let file_name = args.positional(0).expect(
+        "Usage: <binary> input.txt [--boundary=drop|clamp|wrap] [--mod=<num>] [--state=sparse|dense] [--rows] [--render] [--bench]",
+    );
+    let boundary = args.value("boundary").unwrap_or(Boundary::Drop);
+    let manifold =
+        Manifold::parse(FileReader::new(file_name), boundary).unwrap_or_else(|e| panic!("{}", e));
+    let counting = match args.value::<u64>("mod") {
+        None => Counting::Exact,
+        Some(0) => panic!("--mod must be positive"),
+        Some(modulus) => Counting::Modular(modulus),
+    };
+    if args.flag("bench") {
+        bench(&manifold, counting);
+        return;
+    }
+    let representation = args
+        .value("state")
+        .unwrap_or(if manifold.width >= DENSE_WIDTH {
+            Representation::Dense
+        } else {
+            Representation::Sparse
+        });
+    let (split_count, timelines) = simulate_with(
+        representation,
+        &manifold,
+        counting,
+        args.flag("rows"),
+        args.flag("render"),
+    );
     println!("[Part1] Tachyons split {} times", split_count);
-    let timelines = final_state
-        .into_iter()
-        .fold(0, |acc, element| acc + element.1);
-    println!("[Part2] {} timelines are possible", timelines);
+    match counting {
+        Counting::Exact => println!("[Part2] {} timelines are possible", timelines),
+        Counting::Modular(modulus) => println!(
+            "[Part2] {} timelines are possible (mod {})",
+            timelines, modulus
+        ),
+    }
 }
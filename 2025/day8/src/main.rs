@@ -1,40 +1,215 @@
-use std::collections::HashMap;
-use utils::FileReader;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::str::FromStr;
+use utils::{Args, FileReader};
 
-// (x, y, z, id)
-type JunctionBox = (usize, usize, usize, usize);
+// A type junction boxes can be positioned with
+trait Coordinate: Copy + FromStr + Display {
+    type Distance: Copy + Display;
+    fn dist_squared(c1: [Self; 3], c2: [Self; 3]) -> Self::Distance;
+    // Products of two coordinates are squared-distance sized too
+    fn product(c1: Self, c2: Self) -> Self::Distance;
+    fn cmp_dist(d1: &Self::Distance, d2: &Self::Distance) -> Ordering;
+    fn cmp_coord(c1: &Self, c2: &Self) -> Ordering;
+}
+
+impl Coordinate for i64 {
+    // Wide enough that squared distances (and products) can't overflow
+    type Distance = i128;
+    fn dist_squared(c1: [Self; 3], c2: [Self; 3]) -> Self::Distance {
+        c1.into_iter()
+            .zip(c2)
+            .map(|(a, b)| (a as i128 - b as i128).pow(2))
+            .sum()
+    }
+    fn product(c1: Self, c2: Self) -> Self::Distance {
+        c1 as i128 * c2 as i128
+    }
+    fn cmp_dist(d1: &Self::Distance, d2: &Self::Distance) -> Ordering {
+        d1.cmp(d2)
+    }
+    fn cmp_coord(c1: &Self, c2: &Self) -> Ordering {
+        c1.cmp(c2)
+    }
+}
+
+impl Coordinate for f64 {
+    type Distance = f64;
+    fn dist_squared(c1: [Self; 3], c2: [Self; 3]) -> Self::Distance {
+        c1.into_iter().zip(c2).map(|(a, b)| (a - b).powi(2)).sum()
+    }
+    fn product(c1: Self, c2: Self) -> Self::Distance {
+        c1 * c2
+    }
+    fn cmp_dist(d1: &Self::Distance, d2: &Self::Distance) -> Ordering {
+        d1.total_cmp(d2)
+    }
+    fn cmp_coord(c1: &Self, c2: &Self) -> Ordering {
+        c1.total_cmp(c2)
+    }
+}
 
-fn dist_squared(j1: &JunctionBox, j2: &JunctionBox) -> usize {
-    j1.0.abs_diff(j2.0).pow(2) + j1.1.abs_diff(j2.1).pow(2) + j1.2.abs_diff(j2.2).pow(2)
+#[derive(Clone, Copy, Debug)]
+struct JunctionBox<T> {
+    id: usize,
+    x: T,
+    y: T,
+    z: T,
 }
 
-fn get_sorted_connections(boxes: Vec<JunctionBox>) -> Vec<(usize, usize)> {
-    let mut sorted_connections: Vec<(JunctionBox, JunctionBox)> = vec![];
-    sorted_connections.reserve((boxes.len() * boxes.len() - 1) / 2);
+impl<T: Coordinate> JunctionBox<T> {
+    fn parse(coords: &str, id: usize) -> Result<Self, String> {
+        let components = coords.split(",").collect::<Vec<&str>>();
+        let [x, y, z] = components[..] else {
+            return Err(format!(
+                "expected 3 coordinates, found {} in \"{}\"",
+                components.len(),
+                coords
+            ));
+        };
+        let parse_component = |component: &str| {
+            component
+                .trim()
+                .parse::<T>()
+                .map_err(|_| format!("invalid coordinate \"{}\" in \"{}\"", component, coords))
+        };
+        Ok(Self {
+            id,
+            x: parse_component(x)?,
+            y: parse_component(y)?,
+            z: parse_component(z)?,
+        })
+    }
+
+    fn coords(&self) -> [T; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    fn dist_squared(&self, other: &Self) -> T::Distance {
+        T::dist_squared(self.coords(), other.coords())
+    }
+
+    // Lexicographic by (x, y, z)
+    fn cmp_coords(&self, other: &Self) -> Ordering {
+        self.coords()
+            .iter()
+            .zip(other.coords().iter())
+            .map(|(c1, c2)| T::cmp_coord(c1, c2))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+// How to order connections of equal length
+#[derive(Clone, Copy)]
+enum TieBreak {
+    // By the pair of box ids, smaller id first
+    Ids,
+    // By the pair of box coordinates, lexicographically smaller box first
+    Coords,
+}
+
+impl FromStr for TieBreak {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ids" => Ok(TieBreak::Ids),
+            "coords" => Ok(TieBreak::Coords),
+            _ => Err(format!("Invalid tie-breaking policy: {}", s)),
+        }
+    }
+}
+
+type BoxPair<'a, T> = (&'a JunctionBox<T>, &'a JunctionBox<T>);
+
+// The pair with the lexicographically smaller box first
+fn ordered_by_coords<T: Coordinate>((j1, j2): BoxPair<'_, T>) -> BoxPair<'_, T> {
+    if j1.cmp_coords(j2).is_gt() {
+        (j2, j1)
+    } else {
+        (j1, j2)
+    }
+}
+
+impl TieBreak {
+    fn cmp<T: Coordinate>(&self, pair_1: BoxPair<'_, T>, pair_2: BoxPair<'_, T>) -> Ordering {
+        match self {
+            TieBreak::Ids => {
+                let ids = |(j1, j2): BoxPair<'_, T>| (j1.id.min(j2.id), j1.id.max(j2.id));
+                ids(pair_1).cmp(&ids(pair_2))
+            }
+            TieBreak::Coords => {
+                let (sorted_1, sorted_2) = (ordered_by_coords(pair_1), ordered_by_coords(pair_2));
+                sorted_1
+                    .0
+                    .cmp_coords(sorted_2.0)
+                    .then_with(|| sorted_1.1.cmp_coords(sorted_2.1))
+            }
+        }
+    }
+}
+
+// All pairs of boxes as (id1, id2, distance squared), shortest first
+fn get_sorted_connections<T: Coordinate>(
+    boxes: &[JunctionBox<T>],
+    tie_break: TieBreak,
+) -> Vec<(usize, usize, T::Distance)> {
+    let mut sorted_connections: Vec<(usize, usize, T::Distance)> =
+        Vec::with_capacity(boxes.len() * boxes.len().saturating_sub(1) / 2);
     for (idx, j1) in boxes.iter().enumerate() {
         for j2 in boxes.iter().skip(idx + 1) {
-            sorted_connections.push((j1.clone(), j2.clone()));
+            sorted_connections.push((j1.id, j2.id, j1.dist_squared(j2)));
         }
     }
+    // Boxes are indexed by their id
     sorted_connections.sort_by(|pair_1, pair_2| {
-        dist_squared(&pair_1.0, &pair_1.1).cmp(&dist_squared(&pair_2.0, &pair_2.1))
+        T::cmp_dist(&pair_1.2, &pair_2.2).then_with(|| {
+            tie_break.cmp(
+                (&boxes[pair_1.0], &boxes[pair_1.1]),
+                (&boxes[pair_2.0], &boxes[pair_2.1]),
+            )
+        })
     });
-    let result = sorted_connections
-        .into_iter()
-        .map(|pair| (pair.0.3, pair.1.3))
-        .collect();
-    result
+    sorted_connections
+}
+
+// Warns if the last connection made and the first one left out are the same
+// length, as then the tie-breaking policy decides which gets made
+fn warn_on_cutoff_tie<T: Coordinate>(
+    sorted_connections: &[(usize, usize, T::Distance)],
+    num_connections: usize,
+) {
+    let Some(last_made) = num_connections.checked_sub(1) else {
+        return;
+    };
+    if let (Some(made), Some(left_out)) = (
+        sorted_connections.get(last_made),
+        sorted_connections.get(num_connections),
+    ) && T::cmp_dist(&made.2, &left_out.2).is_eq()
+    {
+        eprintln!(
+            "Warning: connections {} ({}-{}) and {} ({}-{}) are tied at distance squared {}; the tie-breaking policy may change Part1",
+            num_connections,
+            made.0,
+            made.1,
+            num_connections + 1,
+            left_out.0,
+            left_out.1,
+            made.2
+        );
+    }
 }
 
 fn make_connection(
     connections: &mut impl Iterator<Item = (usize, usize)>,
     id_to_circuit_mappings: &mut HashMap<usize, usize>,
     circuit_sizes: &mut HashMap<usize, usize>,
-) -> (usize, usize) {
-    let (id1, id2) = connections.next().unwrap();
-    let old_circuit = id_to_circuit_mappings.get(&id2).unwrap().clone();
-    let new_circuit = id_to_circuit_mappings.get(&id1).unwrap().clone();
-    let old_circuit_count = circuit_sizes.get(&old_circuit).unwrap().clone();
+) -> Option<(usize, usize)> {
+    let (id1, id2) = connections.next()?;
+    let old_circuit = *id_to_circuit_mappings.get(&id2).unwrap();
+    let new_circuit = *id_to_circuit_mappings.get(&id1).unwrap();
+    let old_circuit_count = *circuit_sizes.get(&old_circuit).unwrap();
     if new_circuit != old_circuit {
         circuit_sizes
             .entry(new_circuit)
@@ -43,7 +218,7 @@ fn make_connection(
             .entry(old_circuit)
             .and_modify(|count| *count = 0);
         *id_to_circuit_mappings = id_to_circuit_mappings
-            .into_iter()
+            .iter_mut()
             .map(|(id, circuit)| {
                 if *circuit == old_circuit {
                     (*id, new_circuit)
@@ -53,24 +228,47 @@ fn make_connection(
             })
             .collect()
     }
-    (id1, id2)
+    Some((id1, id2))
 }
 
-fn parse_junction_box(coords: String, id: usize) -> JunctionBox {
-    let mut components = coords.split(",");
-    let x = components.next().unwrap().parse().unwrap();
-    let y = components.next().unwrap().parse().unwrap();
-    let z = components.next().unwrap().parse().unwrap();
-    (x, y, z, id)
+// Sizes of all circuits, largest first
+fn sorted_circuit_sizes(circuit_sizes: &HashMap<usize, usize>) -> Vec<usize> {
+    let mut sorted_sizes = circuit_sizes
+        .values()
+        .copied()
+        .filter(|size| *size != 0)
+        .collect::<Vec<usize>>();
+    sorted_sizes.sort_by(|size1, size2| size2.cmp(size1));
+    sorted_sizes
 }
 
-fn three_largest_product(circuit_sizes: HashMap<usize, usize>) -> usize {
-    let mut sorted_sizes = circuit_sizes.into_iter().collect::<Vec<(usize, usize)>>();
-    sorted_sizes.sort_by(|(_, size1), (_, size2)| size2.cmp(size1));
-    sorted_sizes
+fn three_largest_product(circuit_sizes: &HashMap<usize, usize>) -> usize {
+    sorted_circuit_sizes(circuit_sizes)
         .into_iter()
         .take(3)
-        .fold(1, |acc, element| acc * element.1)
+        .product()
+}
+
+// Maps circuit size -> number of circuits of that size
+fn size_histogram(circuit_sizes: &HashMap<usize, usize>) -> BTreeMap<usize, usize> {
+    sorted_circuit_sizes(circuit_sizes)
+        .into_iter()
+        .fold(BTreeMap::new(), |mut histogram, size| {
+            *histogram.entry(size).or_insert(0) += 1;
+            histogram
+        })
+}
+
+// All boxes in the same circuit as the given box, in order
+fn circuit_members(id: usize, id_to_circuit_mappings: &HashMap<usize, usize>) -> Vec<usize> {
+    let circuit = id_to_circuit_mappings[&id];
+    let mut members = id_to_circuit_mappings
+        .iter()
+        .filter(|(_, other_circuit)| **other_circuit == circuit)
+        .map(|(other_id, _)| *other_id)
+        .collect::<Vec<usize>>();
+    members.sort();
+    members
 }
 
 fn more_than_one_circuit(circuit_sizes: &HashMap<usize, usize>) -> bool {
@@ -85,56 +283,130 @@ fn more_than_one_circuit(circuit_sizes: &HashMap<usize, usize>) -> bool {
             return true;
         }
     }
-    return false;
+    false
 }
 
-fn main() {
-    let file_name = std::env::args()
-        .nth(1)
-        .expect("Usage: <binary> input.txt <num_connections, default = 1000>");
-    let num_connections: usize = std::env::args()
-        .nth(2)
-        .unwrap_or("1000".to_string())
-        .parse()
-        .unwrap();
-    let boxes: Vec<JunctionBox> = FileReader::new(file_name.as_str())
-        .into_iter()
-        .enumerate()
-        .map(|(idx, jbox_str)| parse_junction_box(jbox_str, idx))
-        .collect();
+fn run<T: Coordinate>(args: &Args, boxes: Vec<JunctionBox<T>>) {
+    let num_connections: usize = args.value("connections").unwrap_or_else(|| {
+        args.positional(1)
+            .unwrap_or("1000")
+            .parse()
+            .expect("Invalid number of connections")
+    });
+    let num_pairs = boxes.len() * boxes.len().saturating_sub(1) / 2;
+    if num_connections > num_pairs {
+        eprintln!(
+            "Can't make {} connections between {} boxes; there are only {} pairs",
+            num_connections,
+            boxes.len(),
+            num_pairs
+        );
+        std::process::exit(1);
+    }
     let mut id_to_circuit_mappings: HashMap<usize, usize> =
         (0..boxes.len()).map(|id| (id, id)).collect();
     let mut circuit_sizes: HashMap<usize, usize> = (0..boxes.len()).map(|id| (id, 1)).collect();
-    let mut connections = get_sorted_connections(boxes.clone()).into_iter();
+    let sorted_connections =
+        get_sorted_connections(&boxes, args.value("tie-break").unwrap_or(TieBreak::Ids));
+    warn_on_cutoff_tie::<T>(&sorted_connections, num_connections);
+    let mut connections = sorted_connections
+        .into_iter()
+        .map(|(id1, id2, _)| (id1, id2));
 
     let mut connected_ids: (usize, usize) = (0, 0);
+    let mut num_made = 0;
+    let mut first_connected: Option<usize> = None;
     for _ in 0..num_connections {
         connected_ids = make_connection(
             &mut connections,
             &mut id_to_circuit_mappings,
             &mut circuit_sizes,
+        )
+        .expect("Number of connections is checked against the number of pairs");
+        num_made += 1;
+        if first_connected.is_none() && !more_than_one_circuit(&circuit_sizes) {
+            first_connected = Some(num_made);
+        }
+    }
+    println! {"[Part1] Product of largest circuits is {}", three_largest_product(&circuit_sizes)};
+    if let Some(top) = args.value::<usize>("top") {
+        let top_sizes = sorted_circuit_sizes(&circuit_sizes)
+            .into_iter()
+            .take(top)
+            .map(|size| size.to_string())
+            .collect::<Vec<String>>();
+        println!(
+            "[Top] {} largest circuits after {} connections: {}",
+            top,
+            num_made,
+            top_sizes.join(", ")
+        );
+    }
+    if args.flag("histogram") {
+        for (size, count) in size_histogram(&circuit_sizes) {
+            println!(
+                "[Histogram] {} circuit(s) of size {} after {} connections",
+                count, size, num_made
+            );
+        }
+    }
+    if let Some(id) = args.value::<usize>("member") {
+        assert!(id < boxes.len(), "No box with id {}", id);
+        let members = circuit_members(id, &id_to_circuit_mappings)
+            .into_iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>();
+        println!(
+            "[Member] Box {} is in a circuit of {} box(es) after {} connections: {}",
+            id,
+            members.len(),
+            num_made,
+            members.join(", ")
         );
     }
-    println! {"[Part1] Product of largest circuits is {}", three_largest_product(circuit_sizes.clone())};
     while more_than_one_circuit(&circuit_sizes) {
         connected_ids = make_connection(
             &mut connections,
             &mut id_to_circuit_mappings,
             &mut circuit_sizes,
+        )
+        .expect("Connecting every pair leaves a single circuit");
+        num_made += 1;
+    }
+    if args.flag("connected") {
+        println!(
+            "[Connected] All boxes are first connected after {} connections",
+            first_connected.unwrap_or(num_made)
         );
     }
-    let final_x_product = boxes
-        .iter()
-        .find(|jbox| jbox.3 == connected_ids.0)
-        .unwrap()
-        .0
-        * boxes
-            .iter()
-            .find(|jbox| jbox.3 == connected_ids.1)
-            .unwrap()
-            .0;
+    // Boxes are indexed by their id
+    let final_x_product = T::product(boxes[connected_ids.0].x, boxes[connected_ids.1].x);
     println!(
         "[Part2] Product of final connected boxes' X-coord is {}",
         final_x_product
     );
 }
+
+fn parse_boxes<T: Coordinate>(lines: impl Iterator<Item = String>) -> Vec<JunctionBox<T>> {
+    lines
+        .enumerate()
+        .map(|(idx, jbox_str)| {
+            JunctionBox::parse(jbox_str.as_str(), idx).unwrap_or_else(|e| {
+                eprintln!("Line {}: {}", idx + 1, e);
+                std::process::exit(1);
+            })
+        })
+        .collect()
+}
+
+fn main() {
+    let args = Args::new();
+    let file_name = args.positional(0).expect(
+        "Usage: <binary> input.txt [num_connections, default = 1000] [--connections=<num>] [--top=<num>] [--histogram] [--member=<id>] [--connected] [--float] [--tie-break=ids|coords]",
+    );
+    if args.flag("float") {
+        run::<f64>(&args, parse_boxes(FileReader::new(file_name)));
+    } else {
+        run::<i64>(&args, parse_boxes(FileReader::new(file_name)));
+    }
+}
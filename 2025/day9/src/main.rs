@@ -1,6 +1,7 @@
 use itertools::Itertools;
-use std::cmp::{max, min};
-use utils::FileReader;
+use std::cmp::{Reverse, max, max_by_key, min, min_by_key};
+use std::fmt::{self, Write};
+use utils::{Args, FileReader};
 
 type Coordinate = (usize, usize);
 
@@ -72,14 +73,13 @@ fn max_area_coords(coords: Vec<Coordinate>) -> Vec<(Coordinate, Coordinate, usiz
         .combinations(2)
         .map(|coords| (*coords[0], *coords[1], area(*coords[0], *coords[1])))
         .collect::<Vec<(Coordinate, Coordinate, usize)>>();
-    coord_combos.sort_by(|a, b| b.2.cmp(&a.2));
+    coord_combos.sort_by_key(|combo| Reverse(combo.2));
     coord_combos
 }
 
 fn lines(coords: Vec<Coordinate>) -> Vec<(Coordinate, Coordinate)> {
-    let mut line_vec: Vec<(Coordinate, Coordinate)> = vec![];
-    line_vec.reserve(coords.len());
-    let last_coord = coords.last().unwrap().clone();
+    let mut line_vec: Vec<(Coordinate, Coordinate)> = Vec::with_capacity(coords.len());
+    let last_coord = *coords.last().unwrap();
     coords
         .into_iter()
         .fold(last_coord, |prev_coord, new_coord| {
@@ -89,7 +89,151 @@ fn lines(coords: Vec<Coordinate>) -> Vec<(Coordinate, Coordinate)> {
     line_vec
 }
 
-fn enclosed(rect: (Coordinate, Coordinate), lines: &Vec<(Coordinate, Coordinate)>) -> bool {
+// A vertex of the tile loop, along with its (1-indexed) line number
+#[derive(Clone, Copy)]
+struct Vertex {
+    line: usize,
+    coord: Coordinate,
+}
+
+impl fmt::Display for Vertex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} ({},{})", self.line, self.coord.0, self.coord.1)
+    }
+}
+
+enum PolygonError {
+    TooFewVertices(usize),
+    RepeatedVertex(Vertex, Vertex),
+    Diagonal(Vertex, Vertex),
+    // The segment from the last vertex back to the first is diagonal
+    NotClosed(Vertex, Vertex),
+    // The segments on either side of the vertex overlap each other
+    DoublesBack(Vertex),
+    SelfIntersection((Vertex, Vertex), (Vertex, Vertex)),
+    ZeroArea,
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolygonError::TooFewVertices(n) => {
+                write!(f, "expected at least 4 vertices, found {}", n)
+            }
+            PolygonError::RepeatedVertex(v1, v2) => {
+                write!(f, "{} repeats {}", v2, v1)
+            }
+            PolygonError::Diagonal(v1, v2) => {
+                write!(f, "segment from {} to {} is diagonal", v1, v2)
+            }
+            PolygonError::NotClosed(last, first) => write!(
+                f,
+                "loop doesn't close; segment from {} back to {} is diagonal",
+                last, first
+            ),
+            PolygonError::DoublesBack(v) => {
+                write!(f, "loop doubles back on itself at {}", v)
+            }
+            PolygonError::SelfIntersection((v1, v2), (v3, v4)) => write!(
+                f,
+                "segment from {} to {} intersects segment from {} to {}",
+                v1, v2, v3, v4
+            ),
+            PolygonError::ZeroArea => write!(f, "loop encloses no area"),
+        }
+    }
+}
+
+// Orientation as drawn with y increasing downwards
+#[derive(Debug)]
+enum Orientation {
+    Clockwise,
+    CounterClockwise,
+}
+
+fn direction(from: Coordinate, to: Coordinate) -> (i8, i8) {
+    (to.0.cmp(&from.0) as i8, to.1.cmp(&from.1) as i8)
+}
+
+fn segments_touch(seg1: (Coordinate, Coordinate), seg2: (Coordinate, Coordinate)) -> bool {
+    // Axis-aligned segments touch exactly when their bounding boxes overlap
+    max(min(seg1.0.0, seg1.1.0), min(seg2.0.0, seg2.1.0))
+        <= min(max(seg1.0.0, seg1.1.0), max(seg2.0.0, seg2.1.0))
+        && max(min(seg1.0.1, seg1.1.1), min(seg2.0.1, seg2.1.1))
+            <= min(max(seg1.0.1, seg1.1.1), max(seg2.0.1, seg2.1.1))
+}
+
+// Checks that consecutive vertices (wrapping around from the last to the
+// first) form a simple rectilinear polygon, returning its orientation or
+// every problem found
+fn validate(coords: &[Coordinate]) -> Result<Orientation, Vec<PolygonError>> {
+    let n = coords.len();
+    if n < 4 {
+        return Err(vec![PolygonError::TooFewVertices(n)]);
+    }
+    let vertex = |idx: usize| Vertex {
+        line: idx % n + 1,
+        coord: coords[idx % n],
+    };
+    let mut errors = vec![];
+    for idx in 0..n {
+        let (v1, v2) = (vertex(idx), vertex(idx + 1));
+        if v1.coord == v2.coord {
+            // Report the later line as the repeat, even when wrapping around
+            errors.push(PolygonError::RepeatedVertex(
+                min_by_key(v1, v2, |v| v.line),
+                max_by_key(v1, v2, |v| v.line),
+            ));
+        } else if (v1.coord.0 != v2.coord.0) && (v1.coord.1 != v2.coord.1) {
+            if idx == n - 1 {
+                errors.push(PolygonError::NotClosed(v1, v2));
+            } else {
+                errors.push(PolygonError::Diagonal(v1, v2));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        // Later checks assume every segment is axis-aligned and non-empty
+        return Err(errors);
+    }
+    for idx in 0..n {
+        let (before, at, after) = (vertex(idx + n - 1), vertex(idx), vertex(idx + 1));
+        let (in_x, in_y) = direction(before.coord, at.coord);
+        if direction(at.coord, after.coord) == (-in_x, -in_y) {
+            errors.push(PolygonError::DoublesBack(at));
+        }
+    }
+    // Segments next to each other share a vertex, which is already handled
+    for (idx1, idx2) in (0..n).tuple_combinations() {
+        if (idx2 == idx1 + 1) || ((idx1 == 0) && (idx2 == n - 1)) {
+            continue;
+        }
+        let (seg1, seg2) = (
+            (vertex(idx1), vertex(idx1 + 1)),
+            (vertex(idx2), vertex(idx2 + 1)),
+        );
+        if segments_touch((seg1.0.coord, seg1.1.coord), (seg2.0.coord, seg2.1.coord)) {
+            errors.push(PolygonError::SelfIntersection(seg1, seg2));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    // Shoelace formula; positive when clockwise, since y points down
+    let twice_area: i128 = (0..n)
+        .map(|idx| {
+            let (c1, c2) = (vertex(idx).coord, vertex(idx + 1).coord);
+            c1.0 as i128 * c2.1 as i128 - c2.0 as i128 * c1.1 as i128
+        })
+        .sum();
+    match twice_area.signum() {
+        1 => Ok(Orientation::Clockwise),
+        -1 => Ok(Orientation::CounterClockwise),
+        _ => Err(vec![PolygonError::ZeroArea]),
+    }
+}
+
+fn enclosed(rect: (Coordinate, Coordinate), lines: &[(Coordinate, Coordinate)]) -> bool {
     let mut last_exit: Option<Coordinate> = None;
     let mut last_entrance: Option<Coordinate> = None;
     for line in lines.iter() {
@@ -139,10 +283,58 @@ fn enclosed(rect: (Coordinate, Coordinate), lines: &Vec<(Coordinate, Coordinate)
     last_exit.is_none() && last_entrance.is_none()
 }
 
+// Draws the red tile loop, and any rectangles as (corners, stroke color),
+// with each tile as a 1x1 square
+fn write_svg(
+    path: &str,
+    lines: &[(Coordinate, Coordinate)],
+    rects: &[((Coordinate, Coordinate), &str)],
+) -> std::io::Result<()> {
+    let xs = lines.iter().map(|line| line.0.0);
+    let ys = lines.iter().map(|line| line.0.1);
+    let (min_x, max_x) = (xs.clone().min().unwrap_or(0), xs.max().unwrap_or(0));
+    let (min_y, max_y) = (ys.clone().min().unwrap_or(0), ys.max().unwrap_or(0));
+    let padding = max(max_x - min_x, max_y - min_y) / 50 + 1;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        min_x as isize - padding as isize,
+        min_y as isize - padding as isize,
+        max_x - min_x + 1 + 2 * padding,
+        max_y - min_y + 1 + 2 * padding
+    );
+    let points = lines
+        .iter()
+        .map(|line| format!("{}.5,{}.5", line.0.0, line.0.1))
+        .collect::<Vec<String>>()
+        .join(" ");
+    writeln!(
+        svg,
+        "  <polygon points=\"{}\" fill=\"#f4cccc\" stroke=\"#cc0000\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\"/>",
+        points
+    )
+    .unwrap();
+    for ((corner1, corner2), color) in rects {
+        writeln!(
+            svg,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\" vector-effect=\"non-scaling-stroke\"/>",
+            min(corner1.0, corner2.0),
+            min(corner1.1, corner2.1),
+            corner1.0.abs_diff(corner2.0) + 1,
+            corner1.1.abs_diff(corner2.1) + 1,
+            color
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    std::fs::write(path, svg)
+}
+
 fn main() {
-    let file_name = std::env::args().nth(1).expect("Usage: <binary> input.txt");
-    let coords = FileReader::new(file_name.as_str())
-        .into_iter()
+    let args = Args::new();
+    let file_name = args
+        .positional(0)
+        .expect("Usage: <binary> input.txt [--svg=<output.svg>] [--top=<num>] [--validate]");
+    let coords = FileReader::new(file_name)
         .map(|line| {
             let mut components = line.split(",");
             (
@@ -151,12 +343,52 @@ fn main() {
             )
         })
         .collect::<Vec<Coordinate>>();
+    match validate(&coords) {
+        Ok(orientation) if args.flag("validate") => {
+            println!(
+                "[Valid] {} vertices, oriented {:?}",
+                coords.len(),
+                orientation
+            )
+        }
+        Ok(_) => (),
+        Err(errors) => {
+            for e in errors.iter() {
+                eprintln!("Invalid tile loop: {}", e);
+            }
+            std::process::exit(1);
+        }
+    }
     let sorted_coords = max_area_coords(coords.clone());
     println!("[Part1] Max area is {}", sorted_coords[0].2);
     let coord_lines = lines(coords);
     let max_enclosed_rect = sorted_coords
-        .into_iter()
+        .iter()
         .find(|(coord1, coord2, _)| enclosed((*coord1, *coord2), &coord_lines))
         .unwrap();
     println!("[Part2] Max area is {}", max_enclosed_rect.2);
+    if let Some(svg_path) = args.value::<String>("svg") {
+        // Top candidates are gray if enclosed and orange if not, under the
+        // best Part1 (blue) and Part2 (green) rectangles
+        let mut rects: Vec<((Coordinate, Coordinate), &str)> = sorted_coords
+            .iter()
+            .take(args.value("top").unwrap_or(0))
+            .map(|(coord1, coord2, _)| {
+                let rect = (*coord1, *coord2);
+                (
+                    rect,
+                    if enclosed(rect, &coord_lines) {
+                        "#999999"
+                    } else {
+                        "#ff9900"
+                    },
+                )
+            })
+            .rev()
+            .collect();
+        rects.push(((sorted_coords[0].0, sorted_coords[0].1), "#0055ff"));
+        rects.push(((max_enclosed_rect.0, max_enclosed_rect.1), "#00aa00"));
+        write_svg(svg_path.as_str(), &coord_lines, &rects)
+            .unwrap_or_else(|e| panic!("Failed to write {}: {}", svg_path, e));
+    }
 }
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::str::FromStr;
+
+// Command-line arguments, split into positional arguments and
+// `--flag` / `--option=value` style options
+pub struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    pub fn new() -> Self {
+        Self::from_iter(std::env::args().skip(1))
+    }
+
+    pub fn positional(&self, idx: usize) -> Option<&str> {
+        self.positional.get(idx).map(|s| s.as_str())
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    pub fn value<T: FromStr>(&self, name: &str) -> Option<T>
+    where
+        T::Err: Debug,
+    {
+        self.options.get(name).map(|value| {
+            value
+                .as_ref()
+                .unwrap_or_else(|| panic!("--{} requires a value (--{}=<value>)", name, name))
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid value for --{}: {:?}", name, e))
+        })
+    }
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromIterator<String> for Args {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut positional = vec![];
+        let mut options = HashMap::new();
+        for arg in iter {
+            match arg.strip_prefix("--") {
+                None => positional.push(arg),
+                Some(option) => match option.split_once("=") {
+                    None => {
+                        options.insert(option.to_string(), None);
+                    }
+                    Some((name, value)) => {
+                        options.insert(name.to_string(), Some(value.to_string()));
+                    }
+                },
+            }
+        }
+        Self {
+            positional,
+            options,
+        }
+    }
+}
@@ -1,2 +1,8 @@
+mod args;
 mod file_reader;
+mod parallel;
+mod repeated_digits;
+pub use args::Args;
 pub use file_reader::FileReader;
+pub use parallel::MapReduce;
+pub use repeated_digits::{MaybeInvalid, RepeatedDigits, Repetition};
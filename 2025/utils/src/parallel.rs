@@ -0,0 +1,82 @@
+use std::num::NonZero;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+pub trait MapReduce: Iterator + Sized
+where
+    Self::Item: Send,
+{
+    // Maps every item, then combines the results in their original order.
+    // When parallel, one worker per core takes the next unmapped item off a
+    // shared queue whenever it's free, so a few slow items don't hold up the
+    // rest; the results are put back in order before being combined, so the
+    // result is the same as the sequential one
+    fn map_reduce<R, M, F>(self, parallel: bool, map: M, init: R, reduce: F) -> R
+    where
+        R: Send,
+        M: Fn(Self::Item) -> R + Sync,
+        F: Fn(R, R) -> R + Sync,
+    {
+        if !parallel {
+            return self.map(map).fold(init, reduce);
+        }
+        let items: Vec<Mutex<Option<Self::Item>>> =
+            self.map(|item| Mutex::new(Some(item))).collect();
+        let next_item = AtomicUsize::new(0);
+        let num_threads = thread::available_parallelism().map_or(1, NonZero::get);
+        let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..num_threads.min(items.len()))
+                .map(|_| {
+                    let (items, next_item, map) = (&items, &next_item, &map);
+                    scope.spawn(move || {
+                        let mut mapped: Vec<(usize, R)> = vec![];
+                        loop {
+                            let idx = next_item.fetch_add(1, Ordering::Relaxed);
+                            let Some(item) = items.get(idx) else {
+                                return mapped;
+                            };
+                            let item = item.lock().unwrap().take().unwrap();
+                            mapped.push((idx, map(item)));
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                for (idx, result) in handle.join().unwrap() {
+                    results[idx] = Some(result);
+                }
+            }
+        });
+        results
+            .into_iter()
+            .map(|result| result.unwrap())
+            .fold(init, reduce)
+    }
+}
+
+impl<I: Iterator> MapReduce for I where I::Item: Send {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn parallel_matches_sequential() {
+        // Uneven work, and a reduce that depends on order
+        let map = |num: u64| {
+            if num.is_multiple_of(7) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            format!("{},", num * num)
+        };
+        let reduce = |acc: String, element: String| acc + &element;
+        for len in [0, 1, 2, 10, 100] {
+            let sequential = (0..len).map_reduce(false, map, String::new(), reduce);
+            let parallel = (0..len).map_reduce(true, map, String::new(), reduce);
+            assert_eq!(parallel, sequential);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::cmp::{max, min};
+use std::ops::Range;
+
+// Which numbers of repeats of a digit block count as "repeated"
+pub enum Repetition {
+    Exactly(usize),
+    AtLeast(usize),
+    // Given (block length, number of repeats)
+    Matching(Box<dyn Fn(usize, usize) -> bool + Send + Sync>),
+}
+
+impl Repetition {
+    fn allows(&self, block_len: usize, repeats: usize) -> bool {
+        match self {
+            Repetition::Exactly(n) => repeats == *n,
+            Repetition::AtLeast(n) => repeats >= *n,
+            Repetition::Matching(predicate) => predicate(block_len, repeats),
+        }
+    }
+}
+
+// Numbers whose digits (in the given radix) are a single block of digits
+// repeated some number of times, as allowed by the repetition policy
+pub struct RepeatedDigits {
+    radix: u32,
+    repetition: Repetition,
+}
+
+impl RepeatedDigits {
+    pub fn new(radix: u32, repetition: Repetition) -> Self {
+        assert!(radix >= 2, "Radix must be at least 2, got {}", radix);
+        Self { radix, repetition }
+    }
+
+    fn num_digits(&self, num: u64) -> usize {
+        num.checked_ilog(self.radix as u64).unwrap_or(0) as usize + 1
+    }
+
+    // The number with a 1 at the start of each block of `block_len` digits
+    // in a `num_len`-digit number (ex. 1001, 10101 in base 10), if the
+    // repetition policy allows that many blocks
+    fn multiplier(&self, num_len: usize, block_len: usize) -> Option<u128> {
+        if (block_len == 0)
+            || !num_len.is_multiple_of(block_len)
+            || !self.repetition.allows(block_len, num_len / block_len)
+        {
+            return None;
+        }
+        let radix = self.radix as u128;
+        Some((radix.pow(num_len as u32) - 1) / (radix.pow(block_len as u32) - 1))
+    }
+
+    // Generates every repeated number in the range. Each one is
+    // `block * multiplier`, so we only need to walk the valid blocks rather
+    // than every number in the range
+    pub fn in_range(&self, range: Range<u64>) -> impl Iterator<Item = u64> {
+        let radix = self.radix as u128;
+        let (low, high) = (range.start as u128, (range.end as u128).saturating_sub(1));
+        (self.num_digits(range.start)..=self.num_digits(high as u64)).flat_map(move |num_len| {
+            (1..num_len)
+                .filter_map(move |block_len| {
+                    self.multiplier(num_len, block_len)
+                        .map(|multiplier| (block_len, multiplier))
+                })
+                .flat_map(move |(block_len, multiplier)| {
+                    let first_block =
+                        max(radix.pow(block_len as u32 - 1), low.div_ceil(multiplier));
+                    let last_block = min(radix.pow(block_len as u32) - 1, high / multiplier);
+                    (first_block..=last_block)
+                        .map(move |block| block * multiplier)
+                        .filter(move |num| {
+                            // Only generate numbers once, for the smallest block length
+                            !(1..block_len)
+                                .filter_map(|smaller_len| self.multiplier(num_len, smaller_len))
+                                .any(|smaller_multiplier| num % smaller_multiplier == 0)
+                        })
+                        .map(|num| num as u64)
+                })
+        })
+    }
+}
+
+pub trait MaybeInvalid {
+    fn is_invalid(&self, config: &RepeatedDigits) -> bool;
+}
+
+impl MaybeInvalid for u64 {
+    fn is_invalid(&self, config: &RepeatedDigits) -> bool {
+        let num_len = config.num_digits(*self);
+        (1..num_len)
+            .filter_map(|block_len| config.multiplier(num_len, block_len))
+            .any(|multiplier| (*self as u128).is_multiple_of(multiplier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digits(mut num: u64, radix: u64) -> Vec<u64> {
+        let mut digits = vec![num % radix];
+        while num >= radix {
+            num /= radix;
+            digits.push(num % radix);
+        }
+        digits
+    }
+
+    fn brute_force(range: Range<u64>, radix: u32, repetition: &Repetition) -> Vec<u64> {
+        range
+            .filter(|num| {
+                let digits = digits(*num, radix as u64);
+                (1..digits.len()).any(|block_len| {
+                    digits.len().is_multiple_of(block_len)
+                        && repetition.allows(block_len, digits.len() / block_len)
+                        && digits
+                            .chunks(block_len)
+                            .all(|chunk| chunk == &digits[..block_len])
+                })
+            })
+            .collect()
+    }
+
+    fn sorted(nums: impl Iterator<Item = u64>) -> Vec<u64> {
+        let mut nums: Vec<u64> = nums.collect();
+        nums.sort_unstable();
+        nums
+    }
+
+    #[test]
+    fn exactly_twice() {
+        let config = RepeatedDigits::new(10, Repetition::Exactly(2));
+        assert_eq!(sorted(config.in_range(95..116)), vec![99]);
+        assert_eq!(sorted(config.in_range(998..1013)), vec![1010]);
+        assert!(!111.is_invalid(&config));
+        assert!(1111.is_invalid(&config));
+    }
+
+    #[test]
+    fn at_least_twice() {
+        let config = RepeatedDigits::new(10, Repetition::AtLeast(2));
+        assert_eq!(sorted(config.in_range(95..116)), vec![99, 111]);
+        assert_eq!(sorted(config.in_range(998..1013)), vec![999, 1010]);
+        assert_eq!(
+            sorted(config.in_range(2121212118..2121212125)),
+            vec![2121212121]
+        );
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        for radix in [2, 3, 10, 16] {
+            for repetition in [
+                Repetition::Exactly(2),
+                Repetition::Exactly(3),
+                Repetition::AtLeast(2),
+                Repetition::Matching(Box::new(|block_len, repeats| {
+                    (block_len % 2 == 1) && (repeats != 4)
+                })),
+            ] {
+                let expected = brute_force(0..100_000, radix, &repetition);
+                let config = RepeatedDigits::new(radix, repetition);
+                assert_eq!(sorted(config.in_range(0..100_000)), expected);
+                assert_eq!(
+                    (0..100_000)
+                        .filter(|num: &u64| num.is_invalid(&config))
+                        .collect::<Vec<u64>>(),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn empty_and_extreme_ranges() {
+        let config = RepeatedDigits::new(10, Repetition::AtLeast(2));
+        assert_eq!(config.in_range(0..0).count(), 0);
+        assert_eq!(config.in_range(Range { start: 5, end: 3 }).count(), 0);
+        assert_eq!(config.in_range(u64::MAX - 1000..u64::MAX).count(), 0);
+        let config = RepeatedDigits::new(2, Repetition::Exactly(2));
+        let num = 0xFFFF_FFFE_FFFF_FFFE;
+        assert_eq!(
+            config.in_range(num - 1..u64::MAX).collect::<Vec<u64>>(),
+            vec![num]
+        );
+    }
+}